@@ -0,0 +1,19 @@
+use super::error::SwapResult;
+
+/// Run blocking swap operation on the tokio's blocking threads pool
+/// 
+/// Panics of the operation are resumed in the current task
+pub async fn spawn_blocking<T, F>(operation: F) -> SwapResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> SwapResult<T> + Send + 'static
+{
+    match tokio::task::spawn_blocking(operation).await {
+        Ok(result) => result,
+
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+
+        // Runtime is shutting down
+        Err(err) => Err(std::io::Error::other(err).into())
+    }
+}
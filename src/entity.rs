@@ -1,18 +1,70 @@
-use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Instant, Duration};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 
 use super::size::SizeOf;
 use super::inplace_cell::InplaceCell;
 use super::uuid;
 use super::error::{SwapResult, SwapError};
 use super::handle::SwapHandle;
-use super::transformer::SwapTransformer;
+use super::priority::SwapPriority;
+use super::guard::{SwapReadGuard, SwapWriteGuard, SwapPinGuard};
 
 pub struct SwapEntity<T> {
     value: InplaceCell<Option<T>>,
     handle: Arc<SwapHandle<T>>,
     uuid: u64,
-    path: PathBuf
+    key: String,
+
+    /// Stored separately from the value
+    /// so it can be read without locking
+    hot: AtomicBool,
+
+    /// Amount of active pins which
+    /// prevent the entity from flushing
+    pins: AtomicUsize,
+
+    /// True if the value stored in the RAM
+    /// differs from the stored in the swap
+    dirty: AtomicBool,
+
+    /// Incremented every time the value is stored in
+    /// or removed from the RAM, so the swap can be read
+    /// without locking the value
+    version: AtomicUsize,
+
+    /// Detached entities keep their swap
+    /// when they're dropped
+    detached: AtomicBool,
+
+    /// Entity's eviction class
+    priority: AtomicU8,
+
+    /// Time after which the entity can't be accessed
+    expires_at: InplaceCell<Option<Instant>>,
+
+    /// True if the entity was removed from the pool
+    /// by `SwapHandle::expire`
+    expired: AtomicBool,
+
+    /// Memory counted in the pool's used memory
+    /// 
+    /// Updated together with the value so
+    /// it's always equal to the entity's size
+    /// when it's hot, and zero otherwise
+    accounted: AtomicUsize,
+
+    /// Value which is being loaded by `value_async`,
+    /// shared with the concurrent calls so the swap
+    /// of the same entity is read only once
+    #[cfg(feature = "async")]
+    loading: std::sync::Mutex<Option<Arc<tokio::sync::OnceCell<T>>>>,
+
+    /// Entity's `flush` method
+    /// 
+    /// Needed to save detached entities when they're
+    /// dropped because `Drop` can't have trait bounds
+    flusher: fn(&Self) -> SwapResult<()>
 }
 
 impl<T> SwapEntity<T> {
@@ -28,6 +80,12 @@ impl<T> SwapEntity<T> {
         self.uuid
     }
 
+    #[inline]
+    /// Get entity's swap storage key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
     #[inline]
     /// Upgrade entity's rank
     pub fn upgrade(&self) -> u64 {
@@ -39,33 +97,380 @@ impl<T> SwapEntity<T> {
     pub fn rank(&self) -> u64 {
         self.handle.rank_entity(self.uuid)
     }
-}
 
-impl<T> SwapEntity<T> where T: Clone {
     #[inline]
     /// Check if the inner value is stored in the RAM right now
     pub fn is_hot(&self) -> bool {
-        self.value.get_ref().is_some()
+        self.hot.load(Ordering::Acquire)
     }
 
     #[inline]
     /// Check if the inner value is stored on the disk right now
     pub fn is_cold(&self) -> bool {
-        self.value.get_ref().is_none()
+        !self.is_hot()
+    }
+
+    #[inline]
+    /// Check if the entity is pinned in the RAM
+    /// 
+    /// Pinned entities can't be flushed
+    pub fn is_pinned(&self) -> bool {
+        self.pins.load(Ordering::Acquire) > 0
+    }
+
+    #[inline]
+    /// Get entity's eviction class
+    pub fn priority(&self) -> SwapPriority {
+        SwapPriority::from_index(self.priority.load(Ordering::Acquire))
+    }
+
+    #[inline]
+    /// Change entity's eviction class
+    /// 
+    /// The pool flushes entities with lower priority first
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// let metadata = pool.spawn_named("metadata", vec![0; 128]).unwrap();
+    /// let data = pool.spawn_named("data", vec![1; 128]).unwrap();
+    /// 
+    /// metadata.set_priority(SwapPriority::High);
+    /// 
+    /// // Use "data" after "metadata"
+    /// data.value().unwrap();
+    /// 
+    /// // "data" is flushed anyway because it has lower priority
+    /// pool.handle().free(1).unwrap();
+    /// 
+    /// assert!(metadata.is_hot());
+    /// assert!(data.is_cold());
+    /// ```
+    pub fn set_priority(&self, priority: SwapPriority) {
+        self.priority.store(priority.index() as u8, Ordering::Release);
+
+        self.handle.prioritize_entity(self.uuid, priority);
+    }
+
+    #[inline]
+    /// Get time after which the entity can't be accessed
+    pub fn expires_at(&self) -> Option<Instant> {
+        *self.expires_at.get_ref()
+    }
+
+    #[inline]
+    /// Change time after which the entity can't be accessed
+    /// 
+    /// Use `None` to make the entity live forever
+    pub fn set_expires_at(&self, expires_at: Option<Instant>) {
+        self.expires_at.replace_by(expires_at);
+    }
+
+    #[inline]
+    /// Make the entity expire after given time from now
+    pub fn set_ttl(&self, ttl: Duration) {
+        self.set_expires_at(Some(Instant::now() + ttl));
+    }
+
+    #[inline]
+    /// Check if the entity is expired
+    /// 
+    /// Expired entities return `SwapError::Expired`
+    /// when accessed, and are removed from the pool
+    /// by `SwapHandle::expire`
+    pub fn is_expired(&self) -> bool {
+        self.expired.load(Ordering::Acquire) ||
+            self.expires_at().is_some_and(|expires_at| expires_at <= Instant::now())
+    }
+
+    #[inline]
+    /// Return `SwapError::Expired` if the entity is expired
+    fn check_expired(&self) -> SwapResult<()> {
+        if self.is_expired() {
+            return Err(SwapError::Expired);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    /// Check if the entity's value was changed
+    /// since it was last written to the swap
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    /// Detach the entity from its swap
+    /// 
+    /// Detached entities are flushed when dropped,
+    /// and their values are kept in the storage.
+    /// Persistent pools list them in the manifest
+    /// so they can be restored by `SwapPool::open`
+    pub fn detach(&self) {
+        self.detached.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    /// Attach the entity to its swap,
+    /// so the swap will be removed when the entity is dropped
+    pub fn attach(&self) {
+        self.detached.store(false, Ordering::Release);
+    }
+
+    #[inline]
+    /// Check if the entity is detached from its swap
+    pub fn is_detached(&self) -> bool {
+        self.detached.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    /// Get memory counted in the pool's used memory
+    pub(crate) fn accounted(&self) -> usize {
+        self.accounted.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    /// Pin the entity in the RAM
+    /// 
+    /// Pinned entities are never flushed by the pool, but
+    /// they're still counted in its used memory. Explicit
+    /// flushing returns `SwapError::Pinned`. Pinning
+    /// doesn't load the entity, so use `value_allocate`
+    /// to make it hot before pinning
+    /// 
+    /// Every `pin` call must be followed by an `unpin` call.
+    /// Consider using `pin_guard` which does it automatically
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// let entity = pool.spawn(vec![0; 128]).unwrap();
+    /// 
+    /// entity.pin();
+    /// 
+    /// assert!(matches!(pool.handle().flush(), Err(SwapError::Pinned)));
+    /// 
+    /// assert!(entity.is_hot());
+    /// 
+    /// entity.unpin();
+    /// 
+    /// pool.handle().flush().unwrap();
+    /// 
+    /// assert!(entity.is_cold());
+    /// ```
+    pub fn pin(&self) {
+        self.pins.fetch_add(1, Ordering::AcqRel);
+    }
+
+    #[inline]
+    /// Unpin the entity, allowing the pool to flush it
+    /// 
+    /// The entity stays pinned while there's
+    /// another pin or a value guard alive
+    pub fn unpin(&self) {
+        // Ignore unpaired unpins
+        let _ = self.pins.fetch_update(Ordering::AcqRel, Ordering::Acquire, |pins| pins.checked_sub(1));
+    }
+
+    #[inline]
+    /// Pin the entity in the RAM while the returned guard is alive
+    /// 
+    /// See `pin` for details
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// let entity = pool.spawn(vec![0; 128]).unwrap();
+    /// 
+    /// let guard = entity.pin_guard();
+    /// 
+    /// // Pinned entities are not flushed when the pool needs memory
+    /// pool.handle().free(1024).unwrap();
+    /// 
+    /// assert!(entity.is_hot());
+    /// 
+    /// drop(guard);
+    /// 
+    /// pool.handle().free(1024).unwrap();
+    /// 
+    /// assert!(entity.is_cold());
+    /// ```
+    pub fn pin_guard(&self) -> SwapPinGuard<'_, T> {
+        self.pin();
+
+        SwapPinGuard::new(self)
+    }
+
+    #[inline]
+    /// Remove the swap copy of the value if the pool doesn't keep them
+    fn remove_swap(&self) -> SwapResult<()> {
+        if !self.handle.keep_swap() && self.handle.storage().exists(&self.key) {
+            self.handle.storage().remove(&self.key)?;
+        }
+
+        Ok(())
     }
 }
 
 impl<T> SwapEntity<T> where T: Clone + SizeOf {
+    #[inline]
+    /// Get memory used by the entity storing given value
+    fn footprint(&self, value: &Option<T>) -> usize {
+        std::mem::size_of_val(self) +
+            std::mem::size_of_val(&self.value) +
+            value.size_of() +
+            self.key.capacity()
+    }
+
+    #[inline]
+    /// Get memory which should be counted in the pool's used memory
+    fn accounted_size(&self, value: &Option<T>) -> usize {
+        match value {
+            Some(_) => self.footprint(value),
+            None => 0
+        }
+    }
+
+    #[inline]
+    /// Update entity's size in the pool's used memory
+    /// 
+    /// Must be called with the entity's value locked
+    /// every time the stored value is changed
+    pub(crate) fn account(&self, value: &Option<T>) {
+        let size = self.accounted_size(value);
+
+        let prev = self.accounted.swap(size, Ordering::AcqRel);
+
+        self.handle.account(self.uuid, self.priority(), prev, size);
+    }
+
+    #[inline]
+    /// Store new value in the RAM, making the entity hot
+    fn set(&self, value: T) {
+        let mut stored = self.value.get_mut();
+
+        // Removed entities must stay cold
+        if self.expired.load(Ordering::Acquire) {
+            return;
+        }
+
+        *stored = Some(value);
+
+        self.version.fetch_add(1, Ordering::AcqRel);
+        self.account(&stored);
+
+        self.hot.store(true, Ordering::Release);
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    /// Store the value read from the swap
+    /// in the RAM if the entity is still cold
+    /// 
+    /// The value is dropped if the entity was made hot
+    /// or flushed after the given version was loaded
+    fn insert(&self, value: T, version: usize) {
+        let mut stored = self.value.get_mut();
+
+        if self.version.load(Ordering::Acquire) != version {
+            return;
+        }
+
+        // Removed entities must stay cold
+        if stored.is_none() && !self.expired.load(Ordering::Acquire) {
+            *stored = Some(value);
+
+            self.version.fetch_add(1, Ordering::AcqRel);
+            self.account(&stored);
+
+            self.hot.store(true, Ordering::Release);
+
+            // Remove the swap copy if the pool doesn't keep them.
+            // The entity is dirty anyway so the copy will be
+            // overwritten on the next flush if we fail to remove it
+            if !self.handle.keep_swap() {
+                self.dirty.store(true, Ordering::Release);
+
+                let _ = self.handle.storage().remove(&self.key);
+            }
+        }
+    }
+
+    /// Remove expired entity from the pool, its value and swap
+    /// 
+    /// Return `Ok(false)` if the entity is pinned
+    pub(crate) fn remove_expired(&self) -> SwapResult<bool> {
+        // Don't wait for the lock if the entity is pinned
+        // because it can be held by the current thread.
+        // Guards pin the entity before locking it,
+        // so the lock is free for unpinned entities
+        if self.is_pinned() {
+            return Ok(false);
+        }
+
+        let mut value = self.value.get_mut();
+
+        // The entity could be pinned while we were waiting for the lock
+        if self.is_pinned() {
+            return Ok(false);
+        }
+
+        // Entity is already removed
+        if self.expired.swap(true, Ordering::AcqRel) {
+            return Ok(true);
+        }
+
+        value.take();
+
+        self.version.fetch_add(1, Ordering::AcqRel);
+        self.account(&value);
+
+        self.hot.store(false, Ordering::Release);
+        self.dirty.store(false, Ordering::Release);
+
+        drop(value);
+
+        // Remove the entity from the manifest as well
+        self.attach();
+
+        // Removed entity's key could be taken by another entity
+        let owned = self.handle.forget_entity(self, 0);
+
+        if owned && self.handle.storage().exists(&self.key) {
+            self.handle.storage().remove(&self.key)?;
+        }
+
+        Ok(true)
+    }
+
     #[inline]
     /// Get size of the entity's value
     /// 
-    /// Note that it will return swap file's size
+    /// Note that it will return stored value's size
     /// if the entity is cold, which can be wrong
     /// if you use transformer which changes value's size
     pub fn value_size(&self) -> SwapResult<usize> {
         match self.value.get_ref().as_ref() {
             Some(value) => Ok(value.size_of()),
-            None => Ok(usize::try_from(self.path.metadata()?.len()).unwrap())
+            None => Ok(self.handle.storage().size(&self.key)?)
         }
     }
 }
@@ -73,89 +478,366 @@ impl<T> SwapEntity<T> where T: Clone + SizeOf {
 impl<T> SwapEntity<T>
 where
     T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
-    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + 'static,
-    <T as TryInto<Vec<u8>>>::Error: std::error::Error + 'static
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
 {
-    /// Read value from the swap file using transformer
-    fn read_swap(path: &PathBuf, transformer: &dyn SwapTransformer) -> SwapResult<T> {
-        let swap = std::fs::read(path)?;
+    /// Read value from the pool's storage using transformer
+    fn read_swap(key: &str, handle: &SwapHandle<T>) -> SwapResult<T> {
+        let swap = handle.storage().read(key)?;
 
-        let value = transformer.backward(swap)
+        let value = handle.transformer().backward(swap)
             .map_err(SwapError::TransformBackward)?;
 
         T::try_from(value).map_err(|err| SwapError::Deserialize(Box::new(err)))
     }
 
-    /// Write value to the swap file using transformer
-    fn write_swap(path: &PathBuf, value: T, transformer: &dyn SwapTransformer) -> SwapResult<()> {
+    /// Write value to the pool's storage using transformer
+    fn write_swap(key: &str, value: T, handle: &SwapHandle<T>) -> SwapResult<()> {
         let value: Vec<u8> = value.try_into()
             .map_err(|err| SwapError::Serialize(Box::new(err)))?;
 
-        let swap = transformer.forward(value)
+        let swap = handle.transformer().forward(value)
             .map_err(SwapError::TransformForward)?;
 
-        std::fs::write(path, swap)?;
+        handle.storage().write(key, swap)?;
 
         Ok(())
     }
 
-    /// Create new entity and flush it to the disk if there's no space available
-    pub fn create(value: T, handle: Arc<SwapHandle<T>>, path: impl Into<PathBuf>, thread_safe: bool) -> SwapResult<Self> {
-        let path: PathBuf = path.into();
+    /// Clone stored value or read it from the swap file
+    /// 
+    /// Return the value and the entity's version it was loaded at
+    /// 
+    /// The entity is not locked while reading so other threads
+    /// can access it. The swap is read again if the entity was
+    /// made hot or flushed at the same time
+    fn load(&self) -> SwapResult<(T, usize)> {
+        loop {
+            let version = self.version.load(Ordering::Acquire);
 
-        // We expect the path to be unique for each entity
-        let uuid = uuid::get(&path);
+            if let Some(value) = self.value.get_ref().as_ref() {
+                return Ok((value.clone(), version));
+            }
+
+            let value = Self::read_swap(&self.key, &self.handle);
+
+            if self.version.load(Ordering::Acquire) == version {
+                return value.map(|value| (value, version));
+            }
+        }
+    }
+
+    /// Create new entity and flush it to the storage if there's no space available
+    pub fn create(value: T, handle: Arc<SwapHandle<T>>, key: impl Into<String>, thread_safe: bool) -> SwapResult<Self> {
+        let key: String = key.into();
+
+        // We expect the key to be unique for each entity
+        let uuid = uuid::get(&key);
 
         if value.size_of() > handle.available() {
-            Self::write_swap(&path, value, handle.transformer())?;
-
-            Ok(SwapEntity {
-                value: InplaceCell::new(None, thread_safe),
-                handle,
-                uuid,
-                path
-            })
+            Self::write_swap(&key, value, &handle)?;
+
+            Ok(Self::new(None, handle, key, uuid, thread_safe))
         } else {
-            Ok(SwapEntity {
-                value: InplaceCell::new(Some(value), thread_safe),
-                handle,
-                uuid,
-                path
-            })
+            Ok(Self::new(Some(value), handle, key, uuid, thread_safe))
         }
     }
 
+    #[inline]
+    /// Restore cold entity which value is already stored in the pool's storage
+    /// 
+    /// Restored entity is detached, so its value
+    /// is kept in the storage when it's dropped
+    pub fn restore(handle: Arc<SwapHandle<T>>, key: impl Into<String>, uuid: u64, thread_safe: bool) -> Self {
+        let entity = Self::new(None, handle, key.into(), uuid, thread_safe);
+
+        entity.detach();
+
+        entity
+    }
+
+    #[inline]
+    fn new(value: Option<T>, handle: Arc<SwapHandle<T>>, key: String, uuid: u64, thread_safe: bool) -> Self {
+        let hot = value.is_some();
+
+        let entity = Self {
+            value: InplaceCell::new(value, thread_safe),
+            handle,
+            uuid,
+            key,
+            hot: AtomicBool::new(hot),
+            pins: AtomicUsize::new(0),
+
+            // Hot entities are not stored yet
+            dirty: AtomicBool::new(hot),
+
+            detached: AtomicBool::new(false),
+            priority: AtomicU8::new(SwapPriority::default().index() as u8),
+            expires_at: InplaceCell::new(None, thread_safe),
+            expired: AtomicBool::new(false),
+            accounted: AtomicUsize::new(0),
+            version: AtomicUsize::new(0),
+
+            #[cfg(feature = "async")]
+            loading: std::sync::Mutex::new(None),
+
+            flusher: Self::flush
+        };
+
+        let size = entity.accounted_size(&entity.value.get_ref());
+
+        entity.accounted.store(size, Ordering::Release);
+        entity.handle.spawn_entity(uuid, entity.priority(), size);
+
+        entity
+    }
+
     #[inline]
     /// Get entity's value from the RAM or read it from the disk
     /// 
     /// This method will make the entity hot if the pool has
     /// enough memory available, or keep it cold otherwise
+    /// 
+    /// Return `SwapError::Expired` if the entity is expired
     pub fn value(&self) -> SwapResult<T> {
+        self.check_expired()?;
         self.upgrade();
 
-        let value = self.value.update_result(|value| {
-            let raw_value = match value.take() {
-                Some(value) => value,
-                None => Self::read_swap(&self.path, self.handle.transformer())?
-            };
-
-            // Calculate amount of memory which is needed to be freed to store the value
-            let free = raw_value.size_of()
-                .checked_sub(self.handle.available())
-                .unwrap_or_default();
-
-            // Free some memory if it's needed, and store the value
-            // if we have enough space available
-            if free == 0 || self.handle.free(free)? {
-                *value = Some(raw_value.clone());
-            }
+        if let Some(value) = self.value.get_ref().as_ref() {
+            return Ok(value.clone());
+        }
+
+        let (value, version) = self.load()?;
 
-            Ok::<_, SwapError>(raw_value)
-        })?;
+        if self.make_room(value.size_of())? {
+            self.insert(value.clone(), version);
+        }
 
         Ok(value)
     }
 
+    /// Free memory for the value with given size
+    /// 
+    /// Return `true` if there's enough space available
+    fn make_room(&self, size: usize) -> SwapResult<bool> {
+        // Calculate amount of memory which is needed to be freed to store the value
+        let free = size.saturating_sub(self.handle.available());
+
+        // The entity must not be locked here because freeing
+        // can access other entities, including the current one
+        Ok(free == 0 || self.handle.free(free)?)
+    }
+
+    /// Load entity's value to the RAM the same way as `value`,
+    /// but without upgrading the entity and cloning its value
+    pub(crate) fn make_hot(&self) -> SwapResult<()> {
+        self.check_expired()?;
+
+        if self.is_hot() {
+            return Ok(());
+        }
+
+        let (value, version) = self.load()?;
+
+        if self.make_room(value.size_of())? {
+            self.insert(value, version);
+        }
+
+        Ok(())
+    }
+
+    /// Load cold entity's value to the RAM in advance
+    /// 
+    /// Unlike `value`, this method doesn't count as the entity's use,
+    /// and it only flushes entities with lower priority and rank than
+    /// the current one to free the memory. The value is not read
+    /// if there's not enough memory for its stored copy
+    /// 
+    /// Return `true` if the entity is hot
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// let entity = pool.spawn(vec![0; 128]).unwrap();
+    /// 
+    /// entity.flush().unwrap();
+    /// 
+    /// assert!(entity.prefetch().unwrap());
+    /// assert!(entity.is_hot());
+    /// ```
+    pub fn prefetch(&self) -> SwapResult<bool> {
+        self.check_expired()?;
+
+        if self.is_hot() {
+            return Ok(true);
+        }
+
+        // Don't flush entities which are more valuable than the current one
+        let limit = (self.priority(), self.rank());
+
+        // Estimate value's size using its stored copy
+        // so it's not read if there's no memory for it
+        let free = self.handle.storage()
+            .size(&self.key)?
+            .saturating_sub(self.handle.available());
+
+        if free > 0 && !self.handle.free_below(free, Some(limit))? {
+            return Ok(false);
+        }
+
+        let (value, version) = self.load()?;
+
+        // Stored copy can be smaller than the value
+        let free = value.size_of().saturating_sub(self.handle.available());
+
+        if free == 0 || self.handle.free_below(free, Some(limit))? {
+            self.insert(value, version);
+        }
+
+        Ok(self.is_hot())
+    }
+
+    /// Get entity's value from the RAM or read it from the disk,
+    /// and keep it in the RAM while the returned guard is alive
+    /// 
+    /// This method will make the entity hot, even if there's
+    /// not enough free space available in the pool. The value
+    /// is not cloned, and the entity is pinned until the guard
+    /// is dropped so the pool can't flush it
+    /// 
+    /// The entity can't be updated while the guard is alive,
+    /// so trying to do it from the same thread will deadlock
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(0, "");
+    /// 
+    /// let entity = pool.spawn(vec![1, 2, 3]).unwrap();
+    /// 
+    /// assert!(entity.is_cold());
+    /// 
+    /// let value = entity.read().unwrap();
+    /// 
+    /// assert_eq!(value.len(), 3);
+    /// assert!(entity.is_hot());
+    /// 
+    /// // Pinned entities are not flushed
+    /// assert!(matches!(pool.handle().flush(), Err(SwapError::Pinned)));
+    /// 
+    /// assert!(entity.is_hot());
+    /// 
+    /// drop(value);
+    /// 
+    /// pool.handle().flush().unwrap();
+    /// 
+    /// assert!(entity.is_cold());
+    /// ```
+    pub fn read(&self) -> SwapResult<SwapReadGuard<'_, T>> {
+        self.check_expired()?;
+        self.upgrade();
+        self.pin();
+
+        loop {
+            let value = self.value.get_ref();
+
+            if value.is_some() {
+                return Ok(SwapReadGuard::new(self, value));
+            }
+
+            drop(value);
+
+            if let Err(err) = self.allocate() {
+                self.unpin();
+
+                return Err(err);
+            }
+        }
+    }
+
+    /// Get entity's value from the RAM or read it from the disk,
+    /// and keep it in the RAM while the returned guard is alive
+    /// 
+    /// The guard allows to modify the value in place. The entity
+    /// is marked as dirty, and when the guard is dropped the pool
+    /// will free some memory if the value became larger than
+    /// available space
+    /// 
+    /// Same as `read`, the entity is pinned while the guard is alive
+    /// and can't be accessed by other threads
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(0, "");
+    /// 
+    /// let entity = pool.spawn(vec![1, 2, 3]).unwrap();
+    /// 
+    /// assert!(!entity.is_dirty());
+    /// 
+    /// let mut value = entity.write().unwrap();
+    /// 
+    /// value.push(4);
+    /// 
+    /// assert!(entity.is_dirty());
+    /// 
+    /// drop(value);
+    /// 
+    /// // The entity was flushed because the pool has no memory available
+    /// assert!(entity.is_cold());
+    /// assert!(!entity.is_dirty());
+    /// 
+    /// assert_eq!(entity.value().unwrap(), vec![1, 2, 3, 4]);
+    /// ```
+    pub fn write(&self) -> SwapResult<SwapWriteGuard<'_, T>> {
+        self.check_expired()?;
+        self.upgrade();
+        self.pin();
+
+        loop {
+            let value = self.value.get_mut();
+
+            if value.is_some() {
+                self.mark_dirty();
+
+                return Ok(SwapWriteGuard::new(self, value));
+            }
+
+            drop(value);
+
+            if let Err(err) = self.allocate() {
+                self.unpin();
+
+                return Err(err);
+            }
+        }
+    }
+
+    /// Read value from the swap file and store it in the RAM,
+    /// freeing pool's memory if it's needed
+    fn allocate(&self) -> SwapResult<()> {
+        let (value, version) = self.load()?;
+
+        // Calculate amount of memory which is needed to be freed to store the value
+        let free = value.size_of().saturating_sub(self.handle.available());
+
+        if free > 0 {
+            self.handle.free(free)?;
+        }
+
+        self.insert(value, version);
+
+        Ok(())
+    }
+
     #[inline]
     /// Get entity's value from the RAM or read it from the disk,
     /// and flush the value afterwards
@@ -165,12 +847,15 @@ where
     /// 
     /// Use it if you need to access value once
     pub fn value_unallocate(&self) -> SwapResult<T> {
-        self.value.update_result(|value| {
-            match value.take() {
-                Some(value) => Ok(value),
-                None => Self::read_swap(&self.path, self.handle.transformer())
-            }
-        })
+        self.check_expired()?;
+
+        let (value, _) = self.load()?;
+
+        // Pinned entities stay hot
+        match self.flush() {
+            Ok(()) | Err(SwapError::Pinned) => Ok(value),
+            Err(err) => Err(err)
+        }
     }
 
     #[inline]
@@ -181,19 +866,14 @@ where
     /// 
     /// Use it if you need to access value frequently
     pub fn value_allocate(&self) -> SwapResult<T> {
+        self.check_expired()?;
         self.upgrade();
 
-        self.value.update_result(|value| {
-            if value.is_none() {
-                *value = Some(Self::read_swap(&self.path, self.handle.transformer())?);
-            }
+        let (value, version) = self.load()?;
 
-            Ok::<_, SwapError>(())
-        })?;
+        self.insert(value.clone(), version);
 
-        unsafe {
-            Ok(self.value.get_copy().unwrap_unchecked())
-        }
+        Ok(value)
     }
 
     #[inline]
@@ -206,34 +886,35 @@ where
     /// 
     /// Use `replace` instead if you're sure that
     /// it will take less or equal amount of memory
-    // TODO: don't flush the value before we're sure that it's needed
+    /// 
+    /// The entity is kept unchanged if the method failed
+    /// to update its value
     pub fn update(&self, value: T) -> SwapResult<bool> {
-        // Flush the entity making it cold
-        self.flush()?;
+        self.check_expired()?;
 
-        // Calculate amount of memory which is needed to be freed to store the value
-        let free = value.size_of()
-            .checked_sub(self.handle.available() + self.size_of())
+        // Memory used by the current value will be reused by the new one
+        let current = self.value.get_ref()
+            .as_ref()
+            .map(SizeOf::size_of)
             .unwrap_or_default();
 
-        // Free some memory if it's needed, and store the value
-        // if we have enough space available
-        if free == 0 || self.handle.free(free)? {
-            // Replace the value
-            self.value.replace_by(Some(value));
+        // Calculate amount of memory which is needed to be freed to store the value
+        let free = value.size_of().saturating_sub(self.handle.available() + current);
+
+        // Pin the entity so the pool won't flush its old value
+        self.pin();
 
-            // This is technically not needed but I do this anyway
-            // for some ideological consistency
-            if self.path.exists() {
-                std::fs::remove_file(&self.path)?;
-            }
+        let freed = free == 0 || self.handle.free(free).inspect_err(|_| self.unpin())?;
 
-            Ok(true)
-        }
+        self.unpin();
 
-        else {
-            Ok(false)
+        // Store the value if we have enough space available
+        if freed {
+            self.set(value);
+            self.remove_swap()?;
         }
+
+        Ok(freed)
     }
 
     #[inline]
@@ -242,45 +923,253 @@ where
     /// This method will not check if there's enough memory available
     /// so it works faster than `update`
     pub fn replace(&self, value: T) -> SwapResult<()> {
-        self.value.update(move |old_value| *old_value = Some(value));
+        self.check_expired()?;
+
+        self.set(value);
+        self.remove_swap()
+    }
+
+    #[inline]
+    /// Flush stored value to the storage, making current entity cold
+    /// 
+    /// The value is not written if the entity is not dirty
+    /// and the storage still has its copy.
+    /// 
+    /// Return `SwapError::Pinned` if the entity is pinned
+    pub fn flush(&self) -> SwapResult<()> {
+        // Don't wait for the lock if the entity is pinned
+        // because it can be held by the current thread.
+        // Guards pin the entity before locking it,
+        // so the lock is free for unpinned entities
+        if self.is_pinned() {
+            return Err(SwapError::Pinned);
+        }
+
+        let mut value = self.value.get_mut();
+
+        // The entity could be pinned while we were waiting for the lock
+        if self.is_pinned() {
+            return Err(SwapError::Pinned);
+        }
 
-        // This is technically not needed but I do this anyway
-        // for some ideological consistency
-        if self.path.exists() {
-            std::fs::remove_file(&self.path)?;
+        if let Some(stored) = value.as_ref() {
+            // Make loads which are reading the swap right now
+            // retry, since it can be overwritten here
+            self.version.fetch_add(1, Ordering::AcqRel);
+
+            // Don't write the value if it's already stored
+            if self.is_dirty() || !self.handle.storage().exists(&self.key) {
+                // Keep the value until it's written
+                // so it's not lost if writing fails
+                Self::write_swap(&self.key, stored.clone(), &self.handle)?;
+            }
+
+            *value = None;
+
+            self.dirty.store(false, Ordering::Release);
+            self.hot.store(false, Ordering::Release);
+
+            self.account(&value);
         }
 
         Ok(())
     }
+}
+
+#[cfg(feature = "async")]
+impl<T> SwapEntity<T>
+where
+    T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf + Send + Sync + 'static,
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+{
+    /// Get entity's value without blocking the async runtime
+    /// 
+    /// Cold entities are loaded on the tokio's blocking threads.
+    /// Concurrent calls wait for the first one to load the value
+    /// and share it, even if the pool can't keep it in the RAM.
+    /// If loading fails, the next waiting call tries again
+    /// 
+    /// See `SwapEntity::value`
+    /// 
+    /// ```rust
+    /// use std::sync::Arc;
+    /// 
+    /// use swap_pool::prelude::*;
+    /// 
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// let entity = pool.spawn(vec![0; 128]).unwrap();
+    /// 
+    /// entity.flush_async().await.unwrap();
+    /// 
+    /// let tasks = (0..4).map(|_| {
+    ///     let entity = Arc::clone(&entity);
+    /// 
+    ///     tokio::spawn(async move {
+    ///         entity.value_async().await
+    ///     })
+    /// }).collect::<Vec<_>>();
+    /// 
+    /// for task in tasks {
+    ///     assert_eq!(task.await.unwrap().unwrap(), vec![0; 128]);
+    /// }
+    /// 
+    /// assert!(entity.is_hot());
+    /// # }
+    /// ```
+    /// 
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::time::Duration;
+    /// 
+    /// use swap_pool::prelude::*;
+    /// 
+    /// // Count slow reads of the stored values
+    /// #[derive(Default)]
+    /// struct SlowStorage(SwapMemoryStorage, Arc<AtomicUsize>);
+    /// 
+    /// impl SwapStorage for SlowStorage {
+    ///     fn read(&self, key: &str) -> std::io::Result<Vec<u8>> {
+    ///         self.1.fetch_add(1, Ordering::SeqCst);
+    /// 
+    ///         std::thread::sleep(Duration::from_millis(100));
+    /// 
+    ///         self.0.read(key)
+    ///     }
+    /// 
+    ///     fn write(&self, key: &str, data: Vec<u8>) -> std::io::Result<()> { self.0.write(key, data) }
+    ///     fn remove(&self, key: &str) -> std::io::Result<()> { self.0.remove(key) }
+    ///     fn exists(&self, key: &str) -> bool { self.0.exists(key) }
+    ///     fn size(&self, key: &str) -> std::io::Result<usize> { self.0.size(key) }
+    /// }
+    /// 
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let storage = SlowStorage::default();
+    /// let reads = storage.1.clone();
+    /// 
+    /// // The pool can't keep the value in the RAM
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(storage)
+    ///     .build(0, "");
+    /// 
+    /// let entity = pool.spawn(vec![0; 128]).unwrap();
+    /// 
+    /// let tasks = (0..4).map(|_| {
+    ///     let entity = Arc::clone(&entity);
+    /// 
+    ///     tokio::spawn(async move {
+    ///         entity.value_async().await
+    ///     })
+    /// }).collect::<Vec<_>>();
+    /// 
+    /// for task in tasks {
+    ///     assert_eq!(task.await.unwrap().unwrap(), vec![0; 128]);
+    /// }
+    /// 
+    /// // The value was read once and shared between the tasks
+    /// assert!(entity.is_cold());
+    /// assert_eq!(reads.load(Ordering::SeqCst), 1);
+    /// # }
+    /// ```
+    pub async fn value_async(self: &Arc<Self>) -> SwapResult<T> {
+        // Hot entities don't touch the storage,
+        // unless they were flushed in the meantime
+        if self.is_hot() {
+            let entity = self.clone();
+
+            return super::blocking::spawn_blocking(move || entity.value()).await;
+        }
+
+        // Join the value which is being loaded already
+        let loading = self.loading.lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get_or_insert_with(Default::default)
+            .clone();
+
+        let value = loading.get_or_try_init(|| {
+            let entity = self.clone();
+
+            super::blocking::spawn_blocking(move || entity.value())
+        }).await.cloned();
+
+        // Following calls must load the value again
+        let mut current = self.loading.lock().unwrap_or_else(|err| err.into_inner());
+
+        if current.as_ref().is_some_and(|current| Arc::ptr_eq(current, &loading)) {
+            *current = None;
+        }
+
+        value
+    }
 
     #[inline]
-    /// Flush stored value to the disk, making current entity cold
-    pub fn flush(&self) -> SwapResult<()> {
-        self.value.update_result(|value| {
-            if let Some(value) = value.take() {
-                Self::write_swap(&self.path, value, self.handle.transformer())?
-            }
+    /// Update entity's value without blocking the async runtime
+    /// 
+    /// See `SwapEntity::update`
+    pub async fn update_async(self: &Arc<Self>, value: T) -> SwapResult<bool> {
+        let entity = self.clone();
 
-            Ok(())
-        })
+        super::blocking::spawn_blocking(move || entity.update(value)).await
+    }
+
+    #[inline]
+    /// Replace entity's value without blocking the async runtime
+    /// 
+    /// See `SwapEntity::replace`
+    pub async fn replace_async(self: &Arc<Self>, value: T) -> SwapResult<()> {
+        let entity = self.clone();
+
+        super::blocking::spawn_blocking(move || entity.replace(value)).await
+    }
+
+    #[inline]
+    /// Flush entity's value without blocking the async runtime
+    /// 
+    /// See `SwapEntity::flush`
+    pub async fn flush_async(self: &Arc<Self>) -> SwapResult<()> {
+        let entity = self.clone();
+
+        super::blocking::spawn_blocking(move || entity.flush()).await
     }
 }
 
 impl<T> SizeOf for SwapEntity<T> where T: Clone + SizeOf {
     #[inline]
     fn size_of(&self) -> usize {
-        std::mem::size_of_val(self) + 
-            self.value.size_of() +
-            self.path.capacity()
+        self.footprint(&self.value.get_ref())
     }
 }
 
 impl<T> Drop for SwapEntity<T> {
     #[inline]
     fn drop(&mut self) {
-        if self.path.exists() {
+        // Expired entities are already removed from the pool
+        if self.expired.load(Ordering::Acquire) {
+            return;
+        }
+
+        if self.is_detached() {
+            // Dropped entity can't be used anymore
+            // so its pins can't be released
+            *self.pins.get_mut() = 0;
+
+            // TODO: panic?
+            let _ = (self.flusher)(self);
+        }
+
+        let owned = self.handle.forget_entity(self, self.accounted.swap(0, Ordering::AcqRel));
+
+        // Removed entity's key could be taken by another entity
+        if owned && !self.is_detached() && self.handle.storage().exists(&self.key) {
             // TODO: panic?
-            let _ = std::fs::remove_file(&self.path);
+            let _ = self.handle.storage().remove(&self.key);
         }
     }
 }
@@ -7,16 +7,25 @@ pub enum SwapError {
     Io(#[cfg_attr(feature = "thiserror", from)] std::io::Error),
 
     #[cfg_attr(feature = "thiserror", error("Failed to serialize value to bytes: {0}"))]
-    Serialize(#[cfg_attr(feature = "thiserror", source)] Box<dyn std::error::Error + 'static>),
+    Serialize(#[cfg_attr(feature = "thiserror", source)] Box<dyn std::error::Error + Send + Sync + 'static>),
 
     #[cfg_attr(feature = "thiserror", error("Failed to deserialize value from bytes: {0}"))]
-    Deserialize(#[cfg_attr(feature = "thiserror", source)] Box<dyn std::error::Error + 'static>),
+    Deserialize(#[cfg_attr(feature = "thiserror", source)] Box<dyn std::error::Error + Send + Sync + 'static>),
 
     #[cfg_attr(feature = "thiserror", error("Failed to transform value forward: {0}"))]
-    TransformForward(#[cfg_attr(feature = "thiserror", source)] Box<dyn std::error::Error + 'static>),
+    TransformForward(#[cfg_attr(feature = "thiserror", source)] Box<dyn std::error::Error + Send + Sync + 'static>),
 
     #[cfg_attr(feature = "thiserror", error("Failed to transform value backward: {0}"))]
-    TransformBackward(#[cfg_attr(feature = "thiserror", source)] Box<dyn std::error::Error + 'static>)
+    TransformBackward(#[cfg_attr(feature = "thiserror", source)] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[cfg_attr(feature = "thiserror", error("Invalid swap pool manifest: {0}"))]
+    Manifest(String),
+
+    #[cfg_attr(feature = "thiserror", error("Swap pool entity is expired"))]
+    Expired,
+
+    #[cfg_attr(feature = "thiserror", error("Swap pool entity is pinned"))]
+    Pinned
 }
 
 #[cfg(not(feature = "thiserror"))]
@@ -27,7 +36,10 @@ impl std::fmt::Display for SwapError {
             Self::Serialize(error) => write!(f, "Failed to serialize value to bytes: {error}"),
             Self::Deserialize(error) => write!(f, "Failed to deserialize value from bytes: {error}"),
             Self::TransformForward(error) => write!(f, "Failed to transform value forward: {error}"),
-            Self::TransformBackward(error) => write!(f, "Failed to transform value backward: {error}")
+            Self::TransformBackward(error) => write!(f, "Failed to transform value backward: {error}"),
+            Self::Manifest(error) => write!(f, "Invalid swap pool manifest: {error}"),
+            Self::Expired => write!(f, "Swap pool entity is expired"),
+            Self::Pinned => write!(f, "Swap pool entity is pinned")
         }
     }
 }
@@ -49,7 +61,11 @@ impl std::error::Error for SwapError {
             Self::Serialize(error) |
             Self::Deserialize(error) |
             Self::TransformForward(error) |
-            Self::TransformBackward(error) => error.source()
+            Self::TransformBackward(error) => error.source(),
+
+            Self::Manifest(_) |
+            Self::Expired |
+            Self::Pinned => None
         }
     }
 }
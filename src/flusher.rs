@@ -0,0 +1,91 @@
+use std::sync::{Mutex, Condvar};
+use std::time::Duration;
+
+use super::error::SwapError;
+
+#[derive(Debug, Default)]
+struct SwapFlusherState {
+    /// Pool's used memory exceeded the high watermark
+    pending: bool,
+
+    /// Pool was dropped
+    stopped: bool,
+
+    /// Last error happened while flushing
+    error: Option<SwapError>
+}
+
+/// Signal used to wake the pool's background flusher
+#[derive(Debug, Default)]
+pub struct SwapFlusherSignal {
+    state: Mutex<SwapFlusherState>,
+    condvar: Condvar
+}
+
+impl SwapFlusherSignal {
+    /// How often the flusher checks the pool
+    /// if it wasn't notified
+    pub const TIMEOUT: Duration = Duration::from_millis(100);
+
+    #[inline]
+    /// Wake the flusher
+    pub fn notify(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+
+        if !state.pending {
+            state.pending = true;
+
+            self.condvar.notify_one();
+        }
+    }
+
+    #[inline]
+    /// Stop the flusher
+    pub fn stop(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+
+        state.stopped = true;
+
+        self.condvar.notify_one();
+    }
+
+    /// Wait until the flusher is notified or the timeout is reached
+    /// 
+    /// Return `false` if the flusher was stopped
+    pub fn wait(&self) -> bool {
+        let state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+
+        let (mut state, _) = self.condvar.wait_timeout_while(state, Self::TIMEOUT, |state| !state.pending && !state.stopped)
+            .unwrap_or_else(|err| err.into_inner());
+
+        state.pending = false;
+
+        !state.stopped
+    }
+
+    /// Wait until the timeout is reached, ignoring notifications
+    /// 
+    /// Return `false` if the flusher was stopped
+    pub fn sleep(&self) -> bool {
+        let state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+
+        let (mut state, _) = self.condvar.wait_timeout_while(state, Self::TIMEOUT, |state| !state.stopped)
+            .unwrap_or_else(|err| err.into_inner());
+
+        state.pending = false;
+
+        !state.stopped
+    }
+
+    #[inline]
+    /// Save flushing error
+    pub fn report(&self, error: SwapError) {
+        self.state.lock().unwrap_or_else(|err| err.into_inner()).error = Some(error);
+    }
+
+    #[inline]
+    /// Take last flushing error
+    pub fn take_error(&self) -> Option<SwapError> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner()).error.take()
+    }
+}
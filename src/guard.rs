@@ -0,0 +1,176 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use super::size::SizeOf;
+use super::entity::SwapEntity;
+
+/// Keeps the entity pinned in the RAM while alive
+/// 
+/// See `SwapEntity::pin`
+pub struct SwapPinGuard<'a, T> {
+    entity: &'a SwapEntity<T>
+}
+
+impl<'a, T> SwapPinGuard<'a, T> {
+    #[inline]
+    /// The entity must be pinned
+    pub(crate) fn new(entity: &'a SwapEntity<T>) -> Self {
+        Self {
+            entity
+        }
+    }
+
+    #[inline]
+    /// Get pinned entity
+    pub fn entity(&self) -> &SwapEntity<T> {
+        self.entity
+    }
+}
+
+impl<T> Drop for SwapPinGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.entity.unpin();
+    }
+}
+
+/// Shared reference to the entity's value
+/// 
+/// The entity is pinned in the RAM while the guard is alive
+pub struct SwapReadGuard<'a, T> {
+    entity: &'a SwapEntity<T>,
+    value: RwLockReadGuard<'a, Option<T>>
+}
+
+impl<'a, T> SwapReadGuard<'a, T> {
+    #[inline]
+    /// The entity must be pinned and hot
+    pub(crate) fn new(entity: &'a SwapEntity<T>, value: RwLockReadGuard<'a, Option<T>>) -> Self {
+        Self {
+            entity,
+            value
+        }
+    }
+
+    #[inline]
+    /// Get guarded entity
+    pub fn entity(&self) -> &SwapEntity<T> {
+        self.entity
+    }
+}
+
+impl<T> Deref for SwapReadGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // Pinned entities can't be flushed so the value is always here
+        self.value.as_ref()
+            .expect("pinned entity must be hot")
+    }
+}
+
+impl<T> Drop for SwapReadGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.entity.unpin();
+    }
+}
+
+/// Mutable reference to the entity's value
+/// 
+/// The entity is pinned in the RAM while the guard is alive.
+/// When the guard is dropped the pool will free some memory
+/// if the entity's value became too large
+pub struct SwapWriteGuard<'a, T>
+where
+    T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+{
+    entity: &'a SwapEntity<T>,
+
+    /// Always `Some` until the guard is dropped
+    value: Option<RwLockWriteGuard<'a, Option<T>>>
+}
+
+impl<'a, T> SwapWriteGuard<'a, T>
+where
+    T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+{
+    #[inline]
+    /// The entity must be pinned and hot
+    pub(crate) fn new(entity: &'a SwapEntity<T>, value: RwLockWriteGuard<'a, Option<T>>) -> Self {
+        Self {
+            entity,
+            value: Some(value)
+        }
+    }
+
+    #[inline]
+    /// Get guarded entity
+    pub fn entity(&self) -> &SwapEntity<T> {
+        self.entity
+    }
+}
+
+impl<T> Deref for SwapWriteGuard<'_, T>
+where
+    T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // Pinned entities can't be flushed so the value is always here
+        self.value.as_ref()
+            .and_then(|value| value.as_ref())
+            .expect("pinned entity must be hot")
+    }
+}
+
+impl<T> DerefMut for SwapWriteGuard<'_, T>
+where
+    T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Pinned entities can't be flushed so the value is always here
+        self.value.as_mut()
+            .and_then(|value| value.as_mut())
+            .expect("pinned entity must be hot")
+    }
+}
+
+impl<T> Drop for SwapWriteGuard<'_, T>
+where
+    T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+{
+    fn drop(&mut self) {
+        // Unlock the entity before freeing the memory
+        // because the pool can decide to flush it
+        if let Some(value) = self.value.take() {
+            self.entity.account(&value);
+        }
+
+        self.entity.unpin();
+
+        let handle = self.entity.handle();
+
+        // Free memory if the value became too large
+        let used = handle.used();
+
+        if used > handle.allocated() {
+            // TODO: panic?
+            let _ = handle.free(used - handle.allocated());
+        }
+    }
+}
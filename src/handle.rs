@@ -1,46 +1,310 @@
-use std::sync::{Arc, Weak};
+use std::sync::{Arc, Weak, OnceLock};
+use std::time::Instant;
+use std::thread::JoinHandle;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::{HashMap, HashSet, BTreeSet};
+use std::ops::Bound;
 
 use super::size::SizeOf;
 use super::inplace_cell::InplaceCell;
-use super::error::SwapResult;
+use super::error::{SwapResult, SwapError};
 use super::entity::SwapEntity;
-use super::manager::SwapManager;
+use super::manager::{SwapManager, SwapCandidate, SwapCandidates};
 use super::transformer::SwapTransformer;
+use super::storage::SwapStorage;
+use super::manifest::{SwapManifest, SwapManifestEntry, SWAP_MANIFEST_KEY};
+use super::priority::SwapPriority;
+use super::flusher::SwapFlusherSignal;
+use super::workers::SwapWorkers;
+
+struct SwapIndex<T> {
+    entities: HashMap<u64, Weak<SwapEntity<T>>>,
+    names: HashMap<String, u64>,
+
+    /// Swap keys of the alive entities, including
+    /// the ones removed from the names index
+    keys: HashMap<String, u64>
+}
+
+impl<T> Default for SwapIndex<T> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            entities: HashMap::new(),
+            names: HashMap::new(),
+            keys: HashMap::new()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SwapEvictionEntry {
+    priority: SwapPriority,
+    rank: u64,
+    size: usize
+}
+
+/// Hot entities ordered by their priorities and ranks
+#[derive(Default)]
+struct SwapEvictionQueue {
+    /// Priorities, ranks and uuids of the hot entities
+    queue: BTreeSet<(SwapPriority, u64, u64)>,
+
+    /// Hot entities stored in the queue
+    entries: HashMap<u64, SwapEvictionEntry>,
+
+    /// Amounts of the hot entities with each priority
+    counts: [usize; SwapPriority::ALL.len()]
+}
+
+impl SwapEvictionQueue {
+    #[inline]
+    fn insert(&mut self, uuid: u64, entry: SwapEvictionEntry) {
+        self.remove(uuid);
+
+        self.queue.insert((entry.priority, entry.rank, uuid));
+        self.entries.insert(uuid, entry);
+
+        self.counts[entry.priority.index()] += 1;
+    }
+
+    #[inline]
+    fn update(&mut self, uuid: u64, updater: impl FnOnce(&mut SwapEvictionEntry)) {
+        if let Some(mut entry) = self.entries.get(&uuid).copied() {
+            updater(&mut entry);
+
+            self.insert(uuid, entry);
+        }
+    }
+
+    #[inline]
+    fn remove(&mut self, uuid: u64) {
+        if let Some(entry) = self.entries.remove(&uuid) {
+            self.queue.remove(&(entry.priority, entry.rank, uuid));
+
+            self.counts[entry.priority.index()] -= 1;
+        }
+    }
+}
+
+/// Results of a batch operation for every entity's uuid
+/// 
+/// Listed in the same order as the entities were given
+pub type SwapBatchReport<T> = Vec<(u64, SwapResult<T>)>;
+
+/// Hot entities with the same priority
+/// which can be flushed to free the memory
+struct SwapEvictionCandidates<'a> {
+    eviction: &'a SwapEvictionQueue,
+    priority: SwapPriority,
+
+    /// Only entities with lower ranks can be flushed
+    below: Option<u64>,
+
+    /// Entities which were already tried to be flushed
+    skip: &'a HashSet<u64>
+}
+
+impl SwapCandidates for SwapEvictionCandidates<'_> {
+    fn iter(&self) -> Box<dyn Iterator<Item = SwapCandidate> + '_> {
+        let range = match self.below {
+            Some(rank) => (Bound::Included((self.priority, 0, 0)), Bound::Excluded((self.priority, rank, 0))),
+            None => (Bound::Included((self.priority, 0, 0)), Bound::Included((self.priority, u64::MAX, u64::MAX)))
+        };
+
+        Box::new(self.eviction.queue.range(range)
+            .filter(|(_, _, uuid)| !self.skip.contains(uuid))
+            .flat_map(|(_, _, uuid)| self.get(*uuid)))
+    }
+
+    fn get(&self, uuid: u64) -> Option<SwapCandidate> {
+        if self.skip.contains(&uuid) {
+            return None;
+        }
+
+        self.eviction.entries.get(&uuid)
+            .filter(|entry| entry.priority == self.priority)
+            .filter(|entry| self.below.is_none_or(|rank| entry.rank < rank))
+            .map(|entry| SwapCandidate {
+                uuid,
+                priority: entry.priority,
+                rank: entry.rank,
+                size: entry.size
+            })
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        if self.below.is_some() {
+            return self.iter().count();
+        }
+
+        self.eviction.counts[self.priority.index()] - self.skip.iter()
+            .filter(|uuid| self.has_priority(**uuid))
+            .count()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        // Counting candidates below the rank requires iterating over them
+        if self.below.is_some() {
+            return self.iter().next().is_none();
+        }
+
+        self.len() == 0
+    }
+}
+
+impl SwapEvictionCandidates<'_> {
+    #[inline]
+    /// Check if the entity has the candidates' priority
+    fn has_priority(&self, uuid: u64) -> bool {
+        self.eviction.entries.get(&uuid)
+            .is_some_and(|entry| entry.priority == self.priority)
+    }
+}
 
 pub struct SwapHandle<T> {
     allocated: usize,
-    entities: InplaceCell<Vec<Weak<SwapEntity<T>>>>,
+
+    /// Memory used by the hot entities
+    /// 
+    /// Entities update it every time their values change
+    used: AtomicUsize,
+
+    /// Hot entities in the eviction order
+    /// 
+    /// Cold entities are not stored here so
+    /// they're never considered when freeing memory
+    eviction: InplaceCell<SwapEvictionQueue>,
+
+    /// Entities registered in the pool
+    /// 
+    /// Never drop upgraded entities while the index is locked
+    /// because dropped entities remove themselves from it
+    index: InplaceCell<SwapIndex<T>>,
+
     manager: Box<dyn SwapManager>,
-    transformer: Box<dyn SwapTransformer>
+    transformer: Box<dyn SwapTransformer>,
+    storage: Box<dyn SwapStorage>,
+    keep_swap: bool,
+    persistent: bool,
+
+    /// High and low watermarks of the background flusher
+    watermarks: Option<(usize, usize)>,
+
+    /// Background flusher's signal, set when it's started
+    flusher: OnceLock<Arc<SwapFlusherSignal>>,
+
+    /// Threads running batch operations,
+    /// spawned by the first one
+    workers: OnceLock<SwapWorkers>,
+
+    /// Storage keys and uuids of the entities
+    /// which must be listed in the manifest
+    records: InplaceCell<HashMap<String, u64>>
 }
 
 impl<T> SwapHandle<T> {
     #[inline]
     /// Create new swap pool handle
-    pub fn new(allocated: usize, manager: Box<dyn SwapManager>, transformer: Box<dyn SwapTransformer>, thread_safe: bool) -> Self {
+    pub fn new(allocated: usize, manager: Box<dyn SwapManager>, transformer: Box<dyn SwapTransformer>, storage: Box<dyn SwapStorage>, thread_safe: bool) -> Self {
         Self {
             allocated,
-            entities: InplaceCell::new(Vec::new(), thread_safe),
+            used: AtomicUsize::new(0),
+            eviction: InplaceCell::new(SwapEvictionQueue::default(), thread_safe),
+            index: InplaceCell::new(SwapIndex::default(), thread_safe),
             manager,
-            transformer
+            transformer,
+            storage,
+            keep_swap: false,
+            persistent: false,
+            watermarks: None,
+            flusher: OnceLock::new(),
+            workers: OnceLock::new(),
+            records: InplaceCell::new(HashMap::new(), thread_safe)
         }
     }
 
+    #[inline]
+    /// Change swap copies keeping mode
+    /// 
+    /// See `SwapPoolBuilder::with_keep_swap`
+    pub fn with_keep_swap(mut self, keep_swap: bool) -> Self {
+        self.keep_swap = keep_swap;
+
+        self
+    }
+
+    #[inline]
+    /// Change swap pool persistence
+    /// 
+    /// See `SwapPoolBuilder::with_persistent`
+    pub fn with_persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+
+        self
+    }
+
+    #[inline]
+    /// Change background flusher's watermarks
+    /// 
+    /// See `SwapPoolBuilder::with_flusher`
+    pub fn with_flusher(mut self, high: usize, low: usize) -> Self {
+        self.watermarks = Some((high, low.min(high)));
+
+        self
+    }
+
     #[inline]
     /// Register an entity in the swap pool
     pub fn push_entity(&self, entity: SwapEntity<T>) -> Arc<SwapEntity<T>> {
         let entity = Arc::new(entity);
 
-        self.entities.update(|entities| entities.push(Arc::downgrade(&entity)));
-        self.manager.upgrade(entity.uuid());
+        self.index.update(|index| {
+            index.entities.insert(entity.uuid(), Arc::downgrade(&entity));
+            index.names.insert(entity.key().to_string(), entity.uuid());
+            index.keys.insert(entity.key().to_string(), entity.uuid());
+        });
+
+        self.upgrade_entity(entity.uuid());
+
+        if self.persistent {
+            self.records.update(|records| {
+                records.insert(entity.key().to_string(), entity.uuid());
+            });
+        }
 
         entity
     }
 
     #[inline]
     /// Upgrade pool entity's rank and return new value
+    /// 
+    /// Hot entities are moved in the eviction queue
+    /// according to their new rank. Managers are expected
+    /// to change ranks of the upgraded entities only
     pub fn upgrade_entity(&self, uuid: u64) -> u64 {
-        self.manager.upgrade(uuid)
+        let rank = self.manager.upgrade(uuid);
+
+        let queued = self.eviction.get_ref()
+            .entries
+            .get(&uuid)
+            .map(|entry| entry.rank);
+
+        // Don't lock the queue if the entity is cold
+        // or its rank is not changed
+        if queued.is_none() || queued == Some(rank) {
+            return rank;
+        }
+
+        self.eviction.update(|eviction| {
+            // Read the rank again because the entity
+            // could be upgraded by another thread
+            eviction.update(uuid, |entry| entry.rank = self.manager.rank(uuid));
+        });
+
+        rank
     }
 
     #[inline]
@@ -52,7 +316,150 @@ impl<T> SwapHandle<T> {
     #[inline]
     /// Get list of entities registered in the pool
     pub fn entities(&self) -> Vec<Weak<SwapEntity<T>>> {
-        self.entities.get_copy()
+        self.index.get_ref()
+            .entities
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    #[inline]
+    /// Get list of alive entities registered in the pool
+    fn alive_entities(&self) -> Vec<Arc<SwapEntity<T>>> {
+        self.index.get_ref()
+            .entities
+            .values()
+            .flat_map(Weak::upgrade)
+            .collect()
+    }
+
+    #[inline]
+    #[cfg(debug_assertions)]
+    /// Check that the used memory matches the memory accounted by the entities
+    /// 
+    /// Panic if they differ. The pool must not be updated
+    /// by other threads while checking, so it's called
+    /// only when the pool is idle
+    fn check_used(&self) {
+        let used = self.used.load(Ordering::Acquire);
+
+        let accounted = self.alive_entities()
+            .iter()
+            .map(|entity| entity.accounted())
+            .sum::<usize>();
+
+        assert_eq!(used, accounted, "swap pool used memory is {used} but {accounted} is accounted by the entities");
+    }
+
+    #[inline]
+    /// Get pool entity by its swap name
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(128, "");
+    /// 
+    /// let entity = pool.spawn_named("entity", vec![1, 2, 3]).unwrap();
+    /// 
+    /// assert!(pool.handle().contains("entity"));
+    /// assert_eq!(pool.handle().get("entity").unwrap().value().unwrap(), vec![1, 2, 3]);
+    /// 
+    /// // Entities are removed from the pool when dropped
+    /// drop(entity);
+    /// 
+    /// assert!(pool.handle().get("entity").is_none());
+    /// ```
+    pub fn get(&self, name: impl AsRef<str>) -> Option<Arc<SwapEntity<T>>> {
+        let index = self.index.get_ref();
+
+        let uuid = index.names.get(name.as_ref())?;
+
+        index.entities.get(uuid)?.upgrade()
+    }
+
+    #[inline]
+    /// Get pool entity by its unique id
+    pub fn get_by_uuid(&self, uuid: u64) -> Option<Arc<SwapEntity<T>>> {
+        self.index.get_ref()
+            .entities
+            .get(&uuid)?
+            .upgrade()
+    }
+
+    #[inline]
+    /// Check if the pool has an alive entity with the given swap name
+    pub fn contains(&self, name: impl AsRef<str>) -> bool {
+        self.get(name).is_some()
+    }
+
+    #[inline]
+    /// Check if the swap key is used by an alive entity,
+    /// including the ones removed from the names index
+    pub fn is_key_used(&self, key: impl AsRef<str>) -> bool {
+        let index = self.index.get_ref();
+
+        index.keys.get(key.as_ref())
+            .and_then(|uuid| index.entities.get(uuid))
+            .is_some_and(|entity| entity.strong_count() > 0)
+    }
+
+    /// Remove entity with the given swap name from the pool's names index
+    /// 
+    /// The entity is attached to its swap and returned, so its swap will
+    /// be removed when it's dropped. The entity is still managed by the pool
+    /// until then, but it can't be found by its name anymore. Its name can't
+    /// be used by new entities until it's dropped
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(0, "");
+    /// 
+    /// let entity = pool.spawn_named("entity", vec![1; 128]).unwrap();
+    /// 
+    /// drop(entity);
+    /// 
+    /// let entity = pool.spawn_named("entity", vec![2; 128]).unwrap();
+    /// let removed = pool.handle().remove("entity").unwrap();
+    /// 
+    /// assert!(pool.handle().get("entity").is_none());
+    /// 
+    /// // The name is still used by the removed entity
+    /// assert!(pool.spawn_named("entity", vec![3; 128]).is_err());
+    /// 
+    /// drop(entity);
+    /// drop(removed);
+    /// 
+    /// // Dropped entities don't remove the swap of the respawned one
+    /// let entity = pool.spawn_named("entity", vec![3; 128]).unwrap();
+    /// 
+    /// assert!(entity.is_cold());
+    /// assert_eq!(entity.value().unwrap(), vec![3; 128]);
+    /// ```
+    pub fn remove(&self, name: impl AsRef<str>) -> Option<Arc<SwapEntity<T>>> {
+        let name = name.as_ref();
+
+        let entity = {
+            let mut index = self.index.get_mut();
+
+            let uuid = index.names.remove(name)?;
+
+            index.entities.get(&uuid).and_then(Weak::upgrade)
+        }?;
+
+        entity.attach();
+
+        if self.persistent {
+            self.records.update(|records| {
+                records.remove(name);
+            });
+        }
+
+        Some(entity)
     }
 
     #[inline]
@@ -67,6 +474,196 @@ impl<T> SwapHandle<T> {
         self.transformer.as_ref()
     }
 
+    #[inline]
+    /// Get swap pool storage
+    pub fn storage(&self) -> &dyn SwapStorage {
+        self.storage.as_ref()
+    }
+
+    #[inline]
+    /// Check if swap copies of the hot entities are kept in the storage
+    pub fn keep_swap(&self) -> bool {
+        self.keep_swap
+    }
+
+    #[inline]
+    /// Check if the pool saves its manifest so it can be reopened
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    #[inline]
+    /// Get high and low watermarks of the background flusher
+    pub fn watermarks(&self) -> Option<(usize, usize)> {
+        self.watermarks
+    }
+
+    #[inline]
+    /// Take last error happened in the background flusher
+    pub fn take_flusher_error(&self) -> Option<SwapError> {
+        self.flusher.get().and_then(|flusher| flusher.take_error())
+    }
+
+    #[inline]
+    /// Count memory used by the new entity
+    /// 
+    /// Cold entities have zero size
+    pub(crate) fn spawn_entity(&self, uuid: u64, priority: SwapPriority, size: usize) {
+        self.used.fetch_add(size, Ordering::AcqRel);
+
+        self.manager.on_spawn(uuid, size);
+
+        if size > 0 {
+            self.eviction.update(|eviction| eviction.insert(uuid, SwapEvictionEntry {
+                priority,
+                rank: self.manager.rank(uuid),
+                size
+            }));
+        }
+    }
+
+    #[inline]
+    /// Replace entity's size in the used memory
+    /// 
+    /// Entities with zero size are cold and
+    /// removed from the eviction queue
+    pub(crate) fn account(&self, uuid: u64, priority: SwapPriority, prev: usize, size: usize) {
+        if prev == size {
+            return;
+        }
+
+        if size > prev {
+            let used = self.used.fetch_add(size - prev, Ordering::AcqRel) + size - prev;
+
+            // Wake the background flusher
+            if let (Some((high, _)), Some(flusher)) = (self.watermarks, self.flusher.get()) {
+                if used > high {
+                    flusher.notify();
+                }
+            }
+        } else {
+            self.used.fetch_sub(prev - size, Ordering::AcqRel);
+        }
+
+        if prev == 0 {
+            self.manager.on_load(uuid, size);
+        } else if size == 0 {
+            self.manager.on_flush(uuid);
+        } else {
+            self.manager.on_resize(uuid, size);
+        }
+
+        // Ranks can depend on the entities' sizes
+        // so we update them on every change
+        if size > 0 {
+            self.eviction.update(|eviction| eviction.insert(uuid, SwapEvictionEntry {
+                priority,
+                rank: self.manager.rank(uuid),
+                size
+            }));
+        } else {
+            self.eviction.update(|eviction| eviction.remove(uuid));
+        }
+    }
+
+    #[inline]
+    /// Move hot entity to another eviction class
+    pub(crate) fn prioritize_entity(&self, uuid: u64, priority: SwapPriority) {
+        self.eviction.update(|eviction| {
+            eviction.update(uuid, |entry| entry.priority = priority);
+        });
+    }
+
+    #[inline]
+    /// Unregister dropped entity and release its memory
+    /// 
+    /// Attached entities are also removed from the manifest.
+    /// Return `true` if the entity owned its swap key
+    pub(crate) fn forget_entity(&self, entity: &SwapEntity<T>, size: usize) -> bool {
+        self.used.fetch_sub(size, Ordering::AcqRel);
+
+        self.eviction.update(|eviction| eviction.remove(entity.uuid()));
+
+        self.manager.on_remove(entity.uuid());
+
+        let owned = {
+            let mut index = self.index.get_mut();
+
+            index.entities.remove(&entity.uuid());
+
+            if index.names.get(entity.key()) == Some(&entity.uuid()) {
+                index.names.remove(entity.key());
+            }
+
+            let owned = index.keys.get(entity.key()) == Some(&entity.uuid());
+
+            if owned {
+                index.keys.remove(entity.key());
+            }
+
+            owned
+        };
+
+        if self.persistent && !entity.is_detached() {
+            self.records.update(|records| {
+                if records.get(entity.key()) == Some(&entity.uuid()) {
+                    records.remove(entity.key());
+                }
+            });
+        }
+
+        owned
+    }
+
+    /// Get manifest of the entities stored in the pool's storage
+    /// 
+    /// Entities which were never flushed are not listed
+    pub fn manifest(&self) -> SwapResult<SwapManifest> {
+        let Some(transformer) = self.transformer.id() else {
+            return Err(SwapError::Manifest(String::from("pool's transformer has no id")));
+        };
+
+        let mut entities = Vec::new();
+
+        for (key, uuid) in self.records.get_ref().iter() {
+            if self.storage.exists(key) {
+                entities.push(SwapManifestEntry {
+                    uuid: *uuid,
+                    key: key.clone(),
+                    size: self.storage.size(key)?
+                });
+            }
+        }
+
+        Ok(SwapManifest {
+            allocated: self.allocated,
+            transformer: transformer.to_string(),
+            entities
+        })
+    }
+
+    #[inline]
+    /// Write the pool's manifest to its storage
+    /// 
+    /// This is done automatically by persistent pools
+    /// on `flush` and when the pool is dropped
+    pub fn save_manifest(&self) -> SwapResult<()> {
+        let manifest = self.manifest()?;
+
+        self.storage.write(SWAP_MANIFEST_KEY, manifest.to_bytes())?;
+        self.storage.sync()?;
+
+        Ok(())
+    }
+
+    #[inline]
+    /// Reorganize the pool's storage to reduce its fragmentation
+    /// 
+    /// See `SwapStorage::compact`
+    pub fn compact(&self) -> SwapResult<()> {
+        Ok(self.storage.compact()?)
+    }
+
     #[inline]
     /// Get maximum amount of memory which can be allocated by the pool items
     pub fn allocated(&self) -> usize {
@@ -75,91 +672,620 @@ impl<T> SwapHandle<T> {
 
     #[inline]
     /// Remove references to the unused entities
+    /// 
+    /// Dropped entities remove themselves from the pool
+    /// so this is needed only for non thread safe pools
+    /// where parallel updates could be lost
     pub fn collect_garbage(&self) {
-        self.entities.update(|entities| entities.retain(|entity| entity.strong_count() > 0));
+        self.index.update(|index| {
+            index.entities.retain(|_, entity| entity.strong_count() > 0);
+
+            let SwapIndex { entities, names, keys } = index;
+
+            names.retain(|_, uuid| entities.contains_key(uuid));
+            keys.retain(|_, uuid| entities.contains_key(uuid));
+        });
     }
 }
 
 impl<T> SwapHandle<T> where T: Clone + SizeOf {
     #[inline]
-    /// Calculate total amount of memory which is allocated now by the entities
+    /// Get total amount of memory which is allocated now by the entities
+    /// 
+    /// The value is updated by the entities when they're
+    /// loaded, flushed, changed or dropped, so this method
+    /// doesn't iterate over them
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
     /// 
-    /// This method iterates over all the stored entities
+    /// let a = pool.spawn(vec![0; 128]).unwrap();
+    /// let b = pool.spawn(vec![1; 128]).unwrap();
+    /// 
+    /// assert!(pool.handle().used() > 256);
+    /// assert_eq!(pool.handle().used(), pool.handle().used_scan());
+    /// 
+    /// a.flush().unwrap();
+    /// 
+    /// assert!(pool.handle().used() > 128);
+    /// assert_eq!(pool.handle().used(), pool.handle().used_scan());
+    /// 
+    /// b.write().unwrap().extend([1; 128]);
+    /// 
+    /// assert!(pool.handle().used() > 256);
+    /// assert_eq!(pool.handle().used(), pool.handle().used_scan());
+    /// ```
     pub fn used(&self) -> usize {
-        self.entities.get_ref()
+        self.used.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    /// Calculate total amount of memory which is allocated now by the entities
+    /// 
+    /// Unlike `used`, this method iterates over all the stored entities
+    pub fn used_scan(&self) -> usize {
+        self.alive_entities()
             .iter()
-            .flat_map(|weak| weak.upgrade())
             .filter(|entity| entity.is_hot())
-            .map(|entity| entity.size_of())
+            .map(|entity| SwapEntity::size_of(entity))
             .sum()
     }
 
+    /// Remove expired entities from the pool
+    /// 
+    /// Values and swap of the expired entities are removed,
+    /// and they can't be accessed anymore. Pinned entities
+    /// are kept until the next call
+    /// 
+    /// Return amount of removed entities
+    /// 
+    /// ```rust
+    /// use std::time::Duration;
+    /// 
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// let entity = pool.spawn_named_with_ttl("entity", vec![0; 128], Duration::from_millis(10)).unwrap();
+    /// 
+    /// std::thread::sleep(Duration::from_millis(20));
+    /// 
+    /// assert!(matches!(entity.value(), Err(SwapError::Expired)));
+    /// 
+    /// assert_eq!(pool.handle().expire().unwrap(), 1);
+    /// 
+    /// assert!(!pool.handle().contains("entity"));
+    /// assert_eq!(pool.handle().used(), 0);
+    /// ```
+    pub fn expire(&self) -> SwapResult<usize> {
+        let now = Instant::now();
+
+        let mut expired = 0;
+
+        for entity in self.alive_entities() {
+            if entity.expires_at().is_some_and(|expires_at| expires_at <= now) && entity.remove_expired()? {
+                expired += 1;
+            }
+        }
+
+        Ok(expired)
+    }
+
     #[inline]
     /// Calculate memory which is not used to store entities in the RAM
     /// and available for new allocations
-    /// 
-    /// This method iterates over all the stored entities
     pub fn available(&self) -> usize {
-        self.allocated().checked_sub(self.used()).unwrap_or_default()
+        self.allocated().saturating_sub(self.used())
     }
 }
 
 impl<T> SwapHandle<T>
 where
     T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
-    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + 'static,
-    <T as TryInto<Vec<u8>>>::Error: std::error::Error + 'static
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
 {
     #[inline]
-    /// Flush all the stored entities to the disk
+    /// Flush all the stored entities to the storage
+    /// 
+    /// Persistent pools will also save their manifest
+    /// 
+    /// Pinned entities are skipped and `SwapError::Pinned`
+    /// is returned after flushing the others. The manifest
+    /// is not saved in this case because swap of the pinned
+    /// entities can be outdated
     pub fn flush(&self) -> SwapResult<()> {
-        for weak in self.entities.get_ref().iter() {
-            if let Some(entity) = weak.upgrade() {
-                entity.flush()?;
+        let mut pinned = false;
+
+        for entity in self.alive_entities() {
+            match entity.flush() {
+                Ok(()) => (),
+                Err(SwapError::Pinned) => pinned = true,
+                Err(err) => return Err(err)
             }
         }
 
+        if pinned {
+            return Err(SwapError::Pinned);
+        }
+
+        if self.persistent {
+            self.save_manifest()?;
+        }
+
+        Ok(())
+    }
+
+    /// Start background flusher using the pool's watermarks
+    /// 
+    /// The flusher flushes entities when the pool's used memory
+    /// exceeds the high watermark until it drops to the low one.
+    /// It's stopped when the handle is dropped.
+    /// Calling this method again does nothing
+    /// 
+    /// Pools built by `SwapPoolBuilder::build_with_flusher`
+    /// start the flusher automatically
+    /// 
+    /// Return an error if the pool has no watermarks
+    /// or the flusher thread can't be spawned.
+    /// Flushing errors can be read by `take_flusher_error`
+    pub fn start_flusher(self: &Arc<Self>) -> std::io::Result<()> where T: Send + Sync + 'static {
+        let Some((high, low)) = self.watermarks else {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "swap pool has no flusher watermarks"));
+        };
+
+        if self.flusher.get().is_some() {
+            return Ok(());
+        }
+
+        let signal = Arc::new(SwapFlusherSignal::default());
+
+        let flusher = signal.clone();
+        let handle = Arc::downgrade(self);
+
+        // Don't keep the handle alive from the flusher thread
+        std::thread::Builder::new()
+            .name(String::from("swap-pool-flusher"))
+            .spawn(move || {
+                let mut stalled = false;
+
+                loop {
+                    // Don't retry too often if nothing can be flushed
+                    let running = if stalled {
+                        flusher.sleep()
+                    } else {
+                        flusher.wait()
+                    };
+
+                    if !running {
+                        break;
+                    }
+
+                    let Some(handle) = handle.upgrade() else {
+                        break;
+                    };
+
+                    let used = handle.used();
+
+                    stalled = false;
+
+                    if used > high {
+                        match handle.free(used - low) {
+                            Ok(freed) => stalled = !freed,
+
+                            Err(err) => {
+                                flusher.report(err);
+
+                                stalled = true;
+                            }
+                        }
+                    }
+                }
+            })?;
+
+        // The flusher could be started by another thread in the meantime
+        if let Err(signal) = self.flusher.set(signal) {
+            signal.stop();
+        }
+
         Ok(())
     }
 
+    /// Load cold entities with given uuids to the RAM in advance
+    /// 
+    /// Unknown and expired entities are skipped.
+    /// See `SwapEntity::prefetch`
+    /// 
+    /// Return amount of hot entities
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(2048, "");
+    /// 
+    /// let entities = (0..4)
+    ///     .map(|i| pool.spawn_named(format!("chunk {i}"), vec![i; 128]).unwrap())
+    ///     .collect::<Vec<_>>();
+    /// 
+    /// pool.handle().flush().unwrap();
+    /// 
+    /// // Load the next chunks while reading the first one
+    /// let uuids = entities[1..].iter()
+    ///     .map(|entity| entity.uuid())
+    ///     .collect::<Vec<_>>();
+    /// 
+    /// assert_eq!(pool.handle().prefetch(&uuids).unwrap(), 3);
+    /// 
+    /// assert!(entities[0].is_cold());
+    /// assert!(entities[1..].iter().all(|entity| entity.is_hot()));
+    /// ```
+    pub fn prefetch(&self, uuids: &[u64]) -> SwapResult<usize> {
+        let mut prefetched = 0;
+
+        for uuid in uuids {
+            let Some(entity) = self.get_by_uuid(*uuid) else {
+                continue;
+            };
+
+            match entity.prefetch() {
+                Ok(true) => prefetched += 1,
+                Ok(false) | Err(SwapError::Expired) => (),
+
+                Err(err) => return Err(err)
+            }
+        }
+
+        Ok(prefetched)
+    }
+
+    #[inline]
+    /// Load cold entities with given uuids to the RAM
+    /// in advance using a background thread
+    /// 
+    /// See `SwapHandle::prefetch`
+    pub fn prefetch_background(self: &Arc<Self>, uuids: Vec<u64>) -> JoinHandle<SwapResult<usize>>
+    where
+        T: Send + Sync + 'static
+    {
+        let handle = self.clone();
+
+        std::thread::spawn(move || handle.prefetch(&uuids))
+    }
+
+    /// Flush entities with given uuids in parallel
+    /// 
+    /// Unlike `flush`, this method doesn't stop on errors,
+    /// and returns flushing result of every entity.
+    /// Pinned entities are reported with `SwapError::Pinned`,
+    /// and unknown ones with `std::io::ErrorKind::NotFound`
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(4096, "");
+    /// 
+    /// let entities = (0..4)
+    ///     .map(|i| pool.spawn_named(format!("entity {i}"), vec![i; 128]).unwrap())
+    ///     .collect::<Vec<_>>();
+    /// 
+    /// let uuids = entities.iter()
+    ///     .map(|entity| entity.uuid())
+    ///     .collect::<Vec<_>>();
+    /// 
+    /// let report = pool.handle().flush_many(&uuids);
+    /// 
+    /// assert!(report.iter().all(|(_, result)| result.is_ok()));
+    /// assert!(entities.iter().all(|entity| entity.is_cold()));
+    /// 
+    /// // Load flushed entities back
+    /// let report = pool.handle().load_many(&uuids);
+    /// 
+    /// assert!(report.iter().all(|(_, result)| matches!(result, Ok(true))));
+    /// assert!(entities.iter().all(|entity| entity.is_hot()));
+    /// 
+    /// // Unknown entities are reported as well
+    /// let report = pool.handle().flush_many(&[uuids[0], 0]);
+    /// 
+    /// assert!(matches!(report[0], (uuid, Ok(())) if uuid == uuids[0]));
+    /// assert!(matches!(report[1], (0, Err(SwapError::Io(_)))));
+    /// ```
+    pub fn flush_many(&self, uuids: &[u64]) -> SwapBatchReport<()> where T: Send + Sync + 'static {
+        let entities = uuids.iter()
+            .map(|uuid| self.get_by_uuid(*uuid))
+            .collect::<Vec<_>>();
+
+        let known = entities.iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let report = self.batch(&known, SwapEntity::flush);
+
+        Self::report_unknown(uuids, &entities, report)
+    }
+
+    /// Load entities with given uuids to the RAM in parallel
+    /// 
+    /// Every loaded entity counts as used, so entities are
+    /// flushed to free the memory the same way as in
+    /// `SwapEntity::value`. Memory for all the cold entities
+    /// is freed at once before loading them. Entities of the batch
+    /// are pinned while loading so they don't flush each other.
+    /// Returns `true` for every entity which is hot. Unknown
+    /// entities are reported with `std::io::ErrorKind::NotFound`
+    pub fn load_many(&self, uuids: &[u64]) -> SwapBatchReport<bool> where T: Send + Sync + 'static {
+        let found = uuids.iter()
+            .map(|uuid| self.get_by_uuid(*uuid))
+            .collect::<Vec<_>>();
+
+        let entities = found.iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // Pin all the entities so they won't flush each other
+        let pins = entities.iter()
+            .map(|entity| entity.pin_guard())
+            .collect::<Vec<_>>();
+
+        // Upgrade all the entities before loading them
+        let mut memory = 0;
+
+        for entity in &entities {
+            if entity.is_expired() {
+                continue;
+            }
+
+            entity.upgrade();
+
+            // Estimate values' sizes using their stored copies
+            if entity.is_cold() {
+                memory += self.storage.size(entity.key()).unwrap_or_default();
+            }
+        }
+
+        let free = memory.saturating_sub(self.available());
+
+        // Errors are reported by the entities
+        // which can't free memory for themselves
+        if free > 0 {
+            let _ = self.free(free);
+        }
+
+        let report = self.batch(&entities, SwapEntity::make_hot);
+
+        drop(pins);
+
+        let report = report.into_iter()
+            .zip(&entities)
+            .map(|((uuid, result), entity)| (uuid, result.map(|_| entity.is_hot())))
+            .collect();
+
+        Self::report_unknown(uuids, &found, report)
+    }
+
+    /// Run given operation over given entities using
+    /// the pool's worker threads and collect its results
+    /// 
+    /// Worker threads are spawned by the first call. The operation
+    /// runs in the current thread if they can't be spawned
+    fn batch<R: Send + 'static>(&self, entities: &[Arc<SwapEntity<T>>], operation: fn(&SwapEntity<T>) -> SwapResult<R>) -> SwapBatchReport<R> where T: Send + Sync + 'static {
+        if entities.is_empty() {
+            return Vec::new();
+        }
+
+        let workers = match self.workers.get() {
+            Some(workers) => Some(workers),
+
+            None => {
+                let threads = std::thread::available_parallelism()
+                    .map(usize::from)
+                    .unwrap_or(1);
+
+                // Workers could be spawned by another thread in the meantime
+                SwapWorkers::new(threads).ok()
+                    .map(|workers| self.workers.get_or_init(|| workers))
+            }
+        };
+
+        let Some(workers) = workers else {
+            return entities.iter()
+                .map(|entity| (entity.uuid(), operation(entity)))
+                .collect();
+        };
+
+        // Process continuous chunks to keep the report's order
+        let chunk_size = entities.len().div_ceil(workers.threads().min(entities.len()));
+
+        let tasks = entities.chunks(chunk_size)
+            .map(|chunk| {
+                let chunk = chunk.to_vec();
+
+                Box::new(move || {
+                    chunk.iter()
+                        .map(|entity| (entity.uuid(), operation(entity)))
+                        .collect::<Vec<_>>()
+                }) as Box<dyn FnOnce() -> SwapBatchReport<R> + Send>
+            })
+            .collect();
+
+        workers.run(tasks)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Add unknown entities to the batch report
+    /// of the found ones, keeping the uuids' order
+    fn report_unknown<R>(uuids: &[u64], entities: &[Option<Arc<SwapEntity<T>>>], report: SwapBatchReport<R>) -> SwapBatchReport<R> {
+        let mut report = report.into_iter();
+
+        uuids.iter()
+            .zip(entities)
+            .flat_map(|(uuid, entity)| match entity {
+                Some(_) => report.next(),
+
+                None => Some((*uuid, Err(std::io::Error::new(std::io::ErrorKind::NotFound, "swap pool has no entity with given uuid").into())))
+            })
+            .collect()
+    }
+
     /// Free given amount of memory by flushing hot entities
     /// 
+    /// Entities with lower priority are flushed first. Entities
+    /// to flush within each priority are chosen by the pool's
+    /// manager (see `SwapManager::select_victims`). Pinned entities
+    /// are skipped, and the manager is asked again if flushed
+    /// entities didn't free enough memory
+    /// 
     /// If the function returned `Ok(false)` - then the method
     /// failed to free required amount of memory but there's also
     /// no hot entities remained so nothing to unallocate
-    pub fn free(&self, mut memory: usize) -> SwapResult<bool> {
-        // Prepare list of entities and their ranks
-        let mut entities = self.entities.get_ref()
-            .iter()
-            .flat_map(|entity| entity.upgrade())
-            .map(|entity| (self.manager.rank(entity.uuid()), entity))
-            .collect::<Vec<_>>();
+    pub fn free(&self, memory: usize) -> SwapResult<bool> {
+        self.free_below(memory, None)
+    }
+
+    /// Free given amount of memory by flushing hot entities
+    /// which have lower priority and rank than the given ones
+    /// 
+    /// See `SwapHandle::free`
+    pub(crate) fn free_below(&self, mut memory: usize, limit: Option<(SwapPriority, u64)>) -> SwapResult<bool> {
+        // Entities which were already tried to be flushed
+        let mut skip = HashSet::new();
 
-        // Sort entities by their ranks in descending order
-        entities.sort_by(|a, b| b.0.cmp(&a.0));
+        for priority in SwapPriority::ALL {
+            let below = match limit {
+                Some((limit, rank)) if limit == priority => Some(rank),
+                Some((limit, _)) if limit < priority => break,
 
-        // Flush entities one by one until we free enough memory
-        while memory > 0 {
-            let Some((_, entity)) = entities.pop() else {
-                return Ok(false);
+                _ => None
             };
 
-            // Flush entity if it's hot
-            if entity.is_hot() {
-                // Read its size before flushing because it will change after flushing
-                let mut size = entity.size_of();
+            while memory > 0 {
+                let victims = {
+                    let eviction = self.eviction.get_ref();
+
+                    let candidates = SwapEvictionCandidates {
+                        eviction: &eviction,
+                        priority,
+                        below,
+                        skip: &skip
+                    };
+
+                    if candidates.is_empty() {
+                        break;
+                    }
+
+                    // Managers can return any uuids, so keep only
+                    // the ones which are allowed to be flushed
+                    self.manager.select_victims(memory, &candidates)
+                        .into_iter()
+                        .filter(|uuid| candidates.get(*uuid).is_some())
+                        .collect::<Vec<_>>()
+                };
+
+                let mut progress = false;
+
+                // Flush entities one by one until we free enough memory
+                for uuid in victims {
+                    if memory == 0 {
+                        break;
+                    }
+
+                    // Don't try the same entity twice
+                    if !skip.insert(uuid) {
+                        continue;
+                    }
 
-                // Flush the entity
-                entity.flush()?;
+                    progress = true;
 
-                // Decrement flushed entity size to find freed memory size
-                size -= entity.size_of();
+                    let Some(entity) = self.get_by_uuid(uuid) else {
+                        continue;
+                    };
 
-                // We can free more memory than needed so use checked sub here
-                memory = memory.checked_sub(size).unwrap_or_default();
+                    // Flush entity if it's not pinned
+                    if !entity.is_pinned() {
+                        // Read its size before flushing because it will change after flushing
+                        let size = entity.accounted();
+
+                        // Flush the entity. It could be pinned
+                        // by another thread in the meantime
+                        match entity.flush() {
+                            Ok(()) | Err(SwapError::Pinned) => (),
+                            Err(err) => return Err(err)
+                        }
+
+                        // We can free more memory than needed so use checked sub here
+                        memory = memory.saturating_sub(size.saturating_sub(entity.accounted()));
+                    }
+                }
+
+                // Manager refused to choose any new entity
+                if !progress {
+                    break;
+                }
             }
         }
 
-        Ok(true)
+        Ok(memory == 0)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> SwapHandle<T>
+where
+    T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf + Send + Sync + 'static,
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+{
+    #[inline]
+    /// Flush all the stored entities without blocking the async runtime
+    /// 
+    /// See `SwapHandle::flush`
+    pub async fn flush_async(self: &Arc<Self>) -> SwapResult<()> {
+        let handle = self.clone();
+
+        super::blocking::spawn_blocking(move || handle.flush()).await
+    }
+
+    #[inline]
+    /// Free given amount of memory without blocking the async runtime
+    /// 
+    /// See `SwapHandle::free`
+    pub async fn free_async(self: &Arc<Self>, memory: usize) -> SwapResult<bool> {
+        let handle = self.clone();
+
+        super::blocking::spawn_blocking(move || handle.free(memory)).await
+    }
+}
+
+impl<T> Drop for SwapHandle<T> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(flusher) = self.flusher.get() {
+            flusher.stop();
+        }
+
+        // All the entities are dropped already
+        // so nobody can update the pool
+        #[cfg(debug_assertions)]
+        self.check_used();
+
+        // Handle is dropped after all the entities so the manifest
+        // will contain detached entities only
+        if self.persistent {
+            // TODO: panic?
+            let _ = self.save_manifest();
+        }
     }
 }
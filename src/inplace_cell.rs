@@ -1,14 +1,20 @@
-use std::cell::{RefCell, Ref};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, PoisonError, TryLockError};
 
 use super::size::SizeOf;
 
-/// Inplace cells are standard rust `RefCell`-s that
+/// Inplace cells are `RwLock`-backed cells that
 /// are updated "in place", meaning they won't loose
 /// their value until the update is finished.
 /// 
+/// Inplace cells are always `Send + Sync` if the inner
+/// value is, so they can be shared between threads.
+/// 
 /// ### Inplace cell (not thread safe):
 /// 
-/// Foundamentally the same as the standard `RefCell`.
+/// Updated value is taken out of the cell, so the lock
+/// is not held while the updater is running. This allows
+/// the updater to access the cell again, but parallel
+/// updates can overwrite each other.
 /// 
 /// ```text
 /// Thread 1      Thread 2
@@ -27,125 +33,170 @@ use super::size::SizeOf;
 /// 
 /// ### Inplace cell (thread safe):
 /// 
-/// Thread same variant clones inner value for mutating it
-/// so you will get the old variant in a parallel
-/// thread before it was updated. This costs some
-/// performance, which is important for e.g. `SwapManager`-s.
+/// Thread safe variant holds the write lock while the updater
+/// is running, so parallel threads will wait until the update
+/// is finished and no updates will be lost. The updater must not
+/// access the same cell, otherwise it will deadlock.
 /// 
 /// ```text
 /// Thread 1      Thread 2
-/// cell.take()
-///               cell.take()
-///               ^^^^^^^^^^^ - this operation will
-///                             return the same value
-///                             as we initially had
-/// cell.set()
+/// cell.lock()
 ///               cell.get()
 ///               ^^^^^^^^^^ - this operation will
-///                            return updated value
+///                            wait until the cell
+///                            is unlocked
+/// cell.set()
+/// cell.unlock()
+///               ^^^^^^^^^^ - and return updated value
 /// 
 /// ```
 pub struct InplaceCell<T> {
-    value: RefCell<T>,
+    value: RwLock<T>,
 
     /// If true, then the cell's value
-    /// will be cloned before updating
+    /// will be locked while updating
     thread_safe: bool
 }
 
+impl<T> Default for InplaceCell<T> where T: Default {
+    #[inline]
+    fn default() -> Self {
+        Self::new(T::default(), true)
+    }
+}
+
 impl<T> InplaceCell<T> {
     #[inline]
     /// Create new inplace cell
     pub fn new(value: T, thread_safe: bool) -> Self {
         Self {
-            value: RefCell::new(value),
+            value: RwLock::new(value),
             thread_safe
         }
     }
 
+    #[inline]
+    /// Check if the cell is thread safe
+    pub fn is_thread_safe(&self) -> bool {
+        self.thread_safe
+    }
+
     #[inline]
     /// Replace stored value by a new one
-    pub fn replace_by(&self, value: T) {
-        self.value.replace(value);
+    pub fn replace_by(&self, value: T) -> T {
+        std::mem::replace(&mut *self.get_mut(), value)
     }
 
     #[inline]
-    /// Clone stored value and return it
-    pub fn get_ref(&self) -> Ref<'_, T> {
-        self.value.borrow()
+    /// Lock the cell for reading and return
+    /// a reference to the stored value
+    pub fn get_ref(&self) -> RwLockReadGuard<'_, T> {
+        // Updaters can't leave the value in an invalid state
+        // so it's fine to ignore poisoning here
+        self.value.read().unwrap_or_else(PoisonError::into_inner)
     }
-}
 
-impl<T> InplaceCell<T> where T: Default + Clone {
     #[inline]
-    /// Update stored value using updater,
-    /// catching and returning error from the updater
-    /// if it happens
-    pub fn update_result<R, E>(&self, updater: impl FnOnce(&mut T) -> Result<R, E>) -> Result<R, E> {
-        let mut value = self.value.take();
+    /// Lock the cell for writing and return
+    /// a mutable reference to the stored value
+    pub fn get_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.value.write().unwrap_or_else(PoisonError::into_inner)
+    }
 
+    #[inline]
+    /// Try to lock the cell for writing without blocking
+    /// 
+    /// Return `None` if the cell is already locked
+    pub fn try_get_mut(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        match self.value.try_write() {
+            Ok(value) => Some(value),
+            Err(TryLockError::Poisoned(err)) => Some(err.into_inner()),
+            Err(TryLockError::WouldBlock) => None
+        }
+    }
+}
+
+impl<T> InplaceCell<T> where T: Default {
+    #[inline]
+    /// Update stored value using updater
+    pub fn update(&self, updater: impl FnOnce(&mut T)) {
         if self.thread_safe {
-            self.value.replace(value.clone());
+            return updater(&mut self.get_mut());
         }
 
-        let result = updater(&mut value)?;
+        let mut value = std::mem::take(&mut *self.get_mut());
 
-        self.value.replace(value);
+        updater(&mut value);
 
-        Ok(result)
+        self.replace_by(value);
     }
 
     #[inline]
-    /// Replace stored value by updater's result,
+    /// Replace stored value by updater's result
+    pub fn replace(&self, updater: impl FnOnce(T) -> T) {
+        self.update(move |value| *value = updater(std::mem::take(value)));
+    }
+}
+
+impl<T> InplaceCell<T> where T: Default + Clone {
+    #[inline]
+    /// Update stored value using updater,
     /// catching and returning error from the updater
     /// if it happens
-    pub fn replace_result<E>(&self, updater: impl FnOnce(T) -> Result<T, E>) -> Result<(), E> {
-        let value = self.value.take();
-
+    /// 
+    /// The value is cloned before updating and restored
+    /// if the updater fails, so changes made by the failed
+    /// updater are rolled back
+    pub fn update_result<R, E>(&self, updater: impl FnOnce(&mut T) -> Result<R, E>) -> Result<R, E> {
         if self.thread_safe {
-            self.value.replace(value.clone());
+            let mut value = self.get_mut();
+            let backup = value.clone();
+
+            let result = updater(&mut value);
+
+            if result.is_err() {
+                *value = backup;
+            }
+
+            return result;
         }
 
-        self.value.replace(updater(value)?);
+        let mut value = std::mem::take(&mut *self.get_mut());
+        let backup = value.clone();
 
-        Ok(())
-    }
+        let result = updater(&mut value);
 
-    #[inline]
-    /// Update stored value using updater
-    pub fn update(&self, updater: impl FnOnce(&mut T)) {
-        let _ = self.update_result(move |value| {
-            updater(value);
+        self.replace_by(if result.is_ok() { value } else { backup });
 
-            Ok::<_, ()>(())
-        });
+        result
     }
 
     #[inline]
-    /// Replace stored value by updater's result
-    pub fn replace(&self, updater: impl FnOnce(T) -> T) {
-        let _ = self.replace_result(move |value| Ok::<_, ()>(updater(value)));
+    /// Replace stored value by updater's result,
+    /// catching and returning error from the updater
+    /// if it happens
+    /// 
+    /// The value is restored if the updater fails
+    pub fn replace_result<E>(&self, updater: impl FnOnce(T) -> Result<T, E>) -> Result<(), E> {
+        self.update_result(move |value| {
+            *value = updater(std::mem::take(value))?;
+
+            Ok(())
+        })
     }
+}
 
+impl<T> InplaceCell<T> where T: Clone {
     #[inline]
     /// Clone stored value and return it
     pub fn get_copy(&self) -> T {
-        let value = self.value.take();
-
-        self.value.replace(value.clone());
-
-        value
+        self.get_ref().clone()
     }
 }
 
-impl<T> SizeOf for InplaceCell<T> where T: Default + Clone + SizeOf {
+impl<T> SizeOf for InplaceCell<T> where T: SizeOf {
     #[inline]
     fn size_of(&self) -> usize {
-        let value = self.value.take();
-        let size = value.size_of();
-
-        self.value.replace(value);
-
-        std::mem::size_of_val(self) + size
+        std::mem::size_of_val(self) + self.get_ref().size_of()
     }
 }
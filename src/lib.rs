@@ -3,10 +3,20 @@ pub mod inplace_cell;
 pub mod uuid;
 pub mod error;
 pub mod entity;
+pub mod guard;
 pub mod handle;
 pub mod pool;
 pub mod manager;
 pub mod transformer;
+pub mod storage;
+pub mod manifest;
+pub mod priority;
+pub mod flusher;
+
+mod workers;
+
+#[cfg(feature = "async")]
+mod blocking;
 
 pub mod prelude {
     pub use super::size::*;
@@ -14,8 +24,13 @@ pub mod prelude {
     pub use super::uuid;
     pub use super::error::*;
     pub use super::entity::*;
+    pub use super::guard::*;
     pub use super::handle::*;
     pub use super::pool::*;
     pub use super::manager::*;
     pub use super::transformer::*;
+    pub use super::storage::*;
+    pub use super::manifest::*;
+    pub use super::priority::*;
+    pub use super::flusher::*;
 }
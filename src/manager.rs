@@ -1,17 +1,196 @@
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH, Instant, Duration};
 
 use super::inplace_cell::InplaceCell;
+use super::priority::SwapPriority;
+
+/// Hot entity which can be flushed by the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SwapCandidate {
+    /// Entity's unique id
+    pub uuid: u64,
+
+    /// Entity's eviction class
+    /// 
+    /// All the candidates given to the manager
+    /// at once have the same priority
+    pub priority: SwapPriority,
+
+    /// Entity's rank when it was last upgraded or resized
+    pub rank: u64,
+
+    /// Memory used by the entity in the RAM
+    pub size: usize
+}
+
+/// List of hot entities given to `SwapManager::select_victims`
+pub trait SwapCandidates {
+    /// Iterate over candidates in ascending order of their ranks
+    fn iter(&self) -> Box<dyn Iterator<Item = SwapCandidate> + '_>;
+
+    /// Get candidate with given uuid
+    /// 
+    /// Return `None` if the entity is not a candidate
+    fn get(&self, uuid: u64) -> Option<SwapCandidate>;
+
+    /// Get amount of candidates
+    fn len(&self) -> usize;
+
+    #[inline]
+    /// Check if there's no candidates
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
 
 /// Swap manager is needed to rank swap pool entities.
 /// Entities with higher rank will be removed after
 /// entities with lower rank
-pub trait SwapManager {
+/// 
+/// Managers are notified about the entities' lifecycle
+/// using `on_*` methods, which do nothing by default.
+/// Sizes are amounts of memory used by the entities
+/// in the RAM (see `SwapHandle::used`)
+pub trait SwapManager: Send + Sync {
     // Upgrade given entity's rank and return it
     fn upgrade(&self, uuid: u64) -> u64;
 
     /// Rank given entity for the pool garbage collector
     fn rank(&self, uuid: u64) -> u64;
+
+    #[inline]
+    /// Called when a new entity is created
+    /// 
+    /// Size is zero if the entity is cold
+    fn on_spawn(&self, uuid: u64, size: usize) {
+        let _ = (uuid, size);
+    }
+
+    #[inline]
+    /// Called when entity's value is loaded to the RAM
+    fn on_load(&self, uuid: u64, size: usize) {
+        let _ = (uuid, size);
+    }
+
+    #[inline]
+    /// Called when entity's value is flushed to the storage
+    fn on_flush(&self, uuid: u64) {
+        let _ = uuid;
+    }
+
+    #[inline]
+    /// Called when the value of a hot entity changes its size
+    fn on_resize(&self, uuid: u64, size: usize) {
+        let _ = (uuid, size);
+    }
+
+    #[inline]
+    /// Called when the entity is dropped
+    /// 
+    /// Managers should forget all the info
+    /// about the entity here
+    fn on_remove(&self, uuid: u64) {
+        let _ = uuid;
+    }
+
+    /// Choose hot entities which should be flushed
+    /// to free given amount of memory
+    /// 
+    /// Entities are flushed in the returned order.
+    /// The pool can call this method again if flushed
+    /// entities didn't free enough memory (e.g. if some
+    /// of them are pinned), with already tried entities
+    /// removed from the candidates. Returning an empty
+    /// list stops freeing the memory. Returned entities
+    /// which are not listed in the candidates are ignored
+    /// 
+    /// By default entities with the lowest ranks are chosen
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// // Flush the largest entities first
+    /// struct LargestFirstManager;
+    /// 
+    /// impl SwapManager for LargestFirstManager {
+    ///     fn upgrade(&self, _uuid: u64) -> u64 { 0 }
+    ///     fn rank(&self, _uuid: u64) -> u64 { 0 }
+    /// 
+    ///     fn select_victims(&self, _memory: usize, candidates: &dyn SwapCandidates) -> Vec<u64> {
+    ///         candidates.iter()
+    ///             .max_by_key(|candidate| candidate.size)
+    ///             .map(|candidate| vec![candidate.uuid])
+    ///             .unwrap_or_default()
+    ///     }
+    /// }
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_manager(LargestFirstManager)
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// let small = pool.spawn_named("small", vec![0; 16]).unwrap();
+    /// let large = pool.spawn_named("large", vec![0; 512]).unwrap();
+    /// 
+    /// pool.handle().free(1).unwrap();
+    /// 
+    /// assert!(small.is_hot());
+    /// assert!(large.is_cold());
+    /// ```
+    /// 
+    /// ```rust
+    /// use std::sync::Mutex;
+    /// 
+    /// use swap_pool::prelude::*;
+    /// 
+    /// // Flush the latest used entities first, ignoring the candidates
+    /// #[derive(Default)]
+    /// struct GreedyManager(Mutex<Vec<u64>>);
+    /// 
+    /// impl SwapManager for GreedyManager {
+    ///     fn upgrade(&self, uuid: u64) -> u64 {
+    ///         self.0.lock().unwrap().push(uuid);
+    /// 
+    ///         0
+    ///     }
+    /// 
+    ///     fn rank(&self, _uuid: u64) -> u64 { 0 }
+    /// 
+    ///     fn select_victims(&self, _memory: usize, _candidates: &dyn SwapCandidates) -> Vec<u64> {
+    ///         self.0.lock().unwrap().iter().rev().copied().collect()
+    ///     }
+    /// }
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_manager(GreedyManager::default())
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// let low = pool.spawn_named_with_priority("low", vec![0; 128], SwapPriority::Low).unwrap();
+    /// let high = pool.spawn_named_with_priority("high", vec![0; 128], SwapPriority::High).unwrap();
+    /// 
+    /// pool.handle().free(1).unwrap();
+    /// 
+    /// // Entities with higher priority are not candidates
+    /// // while there are ones with lower priority
+    /// assert!(low.is_cold());
+    /// assert!(high.is_hot());
+    /// ```
+    fn select_victims(&self, memory: usize, candidates: &dyn SwapCandidates) -> Vec<u64> {
+        let mut freed = 0;
+
+        candidates.iter()
+            .take_while(|candidate| {
+                let needed = freed < memory;
+
+                freed += candidate.size;
+
+                needed
+            })
+            .map(|candidate| candidate.uuid)
+            .collect()
+    }
 }
 
 /// Rank entities based on their last `upgrade()` call
@@ -20,8 +199,10 @@ pub trait SwapManager {
 /// you get a value of an entity, and return timestamp
 /// in seconds as their ranks
 /// 
-/// If you have a high load system - consider using `SwapUpgradeCountManager`
-/// or implementing your own variant
+/// Entities used within the same second have equal ranks.
+/// If you need exact ordering or have a high load system - consider
+/// using `SwapLruManager`, `SwapUpgradeCountManager` or implementing
+/// your own variant
 pub struct SwapLastUseManager {
     ranks: InplaceCell<HashMap<u64, u64>>
 }
@@ -63,10 +244,95 @@ impl SwapManager for SwapLastUseManager {
             .copied()
             .unwrap_or_default()
     }
+
+    #[inline]
+    fn on_remove(&self, uuid: u64) {
+        self.ranks.update(|ranks| {
+            ranks.remove(&uuid);
+        });
+    }
+}
+
+/// Rank entities based on the order of their `upgrade()` calls
+/// 
+/// Unlike `SwapLastUseManager`, this manager uses a logical
+/// counter instead of the system clock, so entities are
+/// always ranked in the exact order they were used
+/// 
+/// ```rust
+/// use swap_pool::prelude::*;
+/// 
+/// let mut pool = SwapPoolBuilder::default()
+///     .with_manager(SwapLruManager::default())
+///     .with_storage(SwapMemoryStorage::default())
+///     .build(1024, "");
+/// 
+/// let a = pool.spawn_named("a", vec![0; 128]).unwrap();
+/// let b = pool.spawn_named("b", vec![1; 128]).unwrap();
+/// 
+/// // Use "a" after "b"
+/// a.value().unwrap();
+/// 
+/// // "b" is the least recently used one
+/// pool.handle().free(1).unwrap();
+/// 
+/// assert!(a.is_hot());
+/// assert!(b.is_cold());
+/// ```
+pub struct SwapLruManager {
+    counter: AtomicU64,
+    ranks: InplaceCell<HashMap<u64, u64>>
+}
+
+impl Default for SwapLruManager {
+    #[inline]
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl SwapLruManager {
+    #[inline]
+    pub fn new(thread_safe: bool) -> Self {
+        Self {
+            counter: AtomicU64::new(0),
+            ranks: InplaceCell::new(HashMap::new(), thread_safe)
+        }
+    }
+}
+
+impl SwapManager for SwapLruManager {
+    fn upgrade(&self, uuid: u64) -> u64 {
+        let rank = self.counter.fetch_add(1, Ordering::AcqRel) + 1;
+
+        self.ranks.update(move |ranks| {
+            ranks.insert(uuid, rank);
+        });
+
+        rank
+    }
+
+    #[inline]
+    fn rank(&self, uuid: u64) -> u64 {
+        self.ranks.get_ref()
+            .get(&uuid)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn on_remove(&self, uuid: u64) {
+        self.ranks.update(|ranks| {
+            ranks.remove(&uuid);
+        });
+    }
 }
 
 /// Rank entities based on amount of their `upgrade()` calls
 /// 
+/// Counters never decay, so consider using `SwapLfuManager`
+/// if your entities' uses change over time
+/// 
 /// Has better performance than `SwapLastUseManager` because
 /// it just increments a counter in the `HashMap`
 pub struct SwapUpgradeCountManager {
@@ -91,18 +357,15 @@ impl SwapUpgradeCountManager {
 
 impl SwapManager for SwapUpgradeCountManager {
     fn upgrade(&self, uuid: u64) -> u64 {
-        // We always return a value so it's absolutely safe (TM)
-        unsafe {
-            self.ranks.update_result::<u64, ()>(move |ranks| {
-                let rank = ranks.get(&uuid)
-                    .copied()
-                    .unwrap_or_default() + 1;
-    
-                ranks.insert(uuid, rank);
-    
-                Ok(rank)
-            }).unwrap_unchecked()
-        }
+        let mut ranks = self.ranks.get_mut();
+
+        let rank = ranks.get(&uuid)
+            .copied()
+            .unwrap_or_default() + 1;
+
+        ranks.insert(uuid, rank);
+
+        rank
     }
 
     #[inline]
@@ -112,4 +375,865 @@ impl SwapManager for SwapUpgradeCountManager {
             .copied()
             .unwrap_or_default()
     }
+
+    #[inline]
+    fn on_remove(&self, uuid: u64) {
+        self.ranks.update(|ranks| {
+            ranks.remove(&uuid);
+        });
+    }
+}
+
+/// Rank entities based on amount of their `upgrade()` calls
+/// which decays over time
+/// 
+/// Each use of an entity is counted with a weight which halves
+/// every `half_life`, so entities which were used a lot a long
+/// time ago can be flushed in favour of the recently used ones
+/// 
+/// Counters are stored in the log domain, so entities' ranks
+/// don't change between their uses
+/// 
+/// ```rust
+/// use std::time::Duration;
+/// 
+/// use swap_pool::prelude::*;
+/// 
+/// let mut pool = SwapPoolBuilder::default()
+///     .with_manager(SwapLfuManager::default().with_half_life(Duration::from_millis(20)))
+///     .with_storage(SwapMemoryStorage::default())
+///     .build(1024, "");
+/// 
+/// let old = pool.spawn_named("old", vec![0; 128]).unwrap();
+/// let new = pool.spawn_named("new", vec![1; 128]).unwrap();
+/// 
+/// // Use "old" entity a lot
+/// for _ in 0..16 {
+///     old.value().unwrap();
+/// }
+/// 
+/// std::thread::sleep(Duration::from_millis(400));
+/// 
+/// // Use "new" entity a bit
+/// new.value().unwrap();
+/// 
+/// // "old" entity was used more, but its uses decayed
+/// pool.handle().free(1).unwrap();
+/// 
+/// assert!(old.is_cold());
+/// assert!(new.is_hot());
+/// ```
+pub struct SwapLfuManager {
+    created_at: Instant,
+    half_life: Duration,
+
+    /// Logarithms of the entities' uses counters
+    /// 
+    /// Stored as `log2(sum(2^(t / half_life)))`
+    /// for every use time `t`
+    scores: InplaceCell<HashMap<u64, f64>>
+}
+
+impl Default for SwapLfuManager {
+    #[inline]
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl SwapLfuManager {
+    /// Fixed point scale of the ranks
+    const SCALE: f64 = (1u64 << 32) as f64;
+
+    #[inline]
+    /// Create new manager with 1 hour half-life
+    pub fn new(thread_safe: bool) -> Self {
+        Self {
+            created_at: Instant::now(),
+            half_life: Duration::from_secs(3600),
+            scores: InplaceCell::new(HashMap::new(), thread_safe)
+        }
+    }
+
+    #[inline]
+    /// Change time needed for the uses counters to halve
+    pub fn with_half_life(self, half_life: Duration) -> Self {
+        Self {
+            half_life,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Get time needed for the uses counters to halve
+    pub fn half_life(&self) -> Duration {
+        self.half_life
+    }
+
+    #[inline]
+    /// Get current time in half-lives
+    fn now(&self) -> f64 {
+        self.created_at.elapsed().as_secs_f64() / self.half_life.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    #[inline]
+    /// Get decayed amount of the entity's uses
+    pub fn frequency(&self, uuid: u64) -> f64 {
+        self.scores.get_ref()
+            .get(&uuid)
+            .map(|score| (score - self.now()).exp2())
+            .unwrap_or_default()
+    }
+}
+
+impl SwapManager for SwapLfuManager {
+    fn upgrade(&self, uuid: u64) -> u64 {
+        let now = self.now();
+
+        let mut scores = self.scores.get_mut();
+
+        let score = match scores.get(&uuid) {
+            // log2(2^a + 2^b) = max(a, b) + log2(1 + 2^(-|a - b|))
+            Some(score) => score.max(now) + (-(score - now).abs()).exp2().ln_1p() / std::f64::consts::LN_2,
+            None => now
+        };
+
+        scores.insert(uuid, score);
+
+        (score * Self::SCALE) as u64
+    }
+
+    #[inline]
+    fn rank(&self, uuid: u64) -> u64 {
+        self.scores.get_ref()
+            .get(&uuid)
+            .map(|score| (score * Self::SCALE) as u64)
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn on_remove(&self, uuid: u64) {
+        self.scores.update(|scores| {
+            scores.remove(&uuid);
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SwapGreedyDualEntry {
+    /// Memory used by the entity in the RAM
+    size: usize,
+
+    /// Amount of uses since the entity was loaded
+    hits: u64,
+
+    rank: u64
+}
+
+/// Rank entities based on their sizes, uses and reload costs
+/// 
+/// This is a GreedyDual-Size-Frequency manager. Each entity
+/// gets `L + hits * cost / size` rank when it's used or loaded,
+/// where `L` is the rank of the last flushed entity. Large
+/// entities which are not used often are flushed first, and
+/// ranks of unused entities are aged by increasing `L`
+/// 
+/// By default every entity has the same reload cost, so the
+/// manager frees the most memory with the least amount of
+/// flushes. Use `with_cost` to take reload time into account
+/// 
+/// ```rust
+/// use swap_pool::prelude::*;
+/// 
+/// let mut pool = SwapPoolBuilder::default()
+///     .with_manager(SwapGreedyDualSizeManager::default())
+///     .with_storage(SwapMemoryStorage::default())
+///     .build(8192, "");
+/// 
+/// let large = pool.spawn_named("large", vec![0; 4096]).unwrap();
+/// let small = pool.spawn_named("small", vec![1; 16]).unwrap();
+/// 
+/// // Use "large" after "small"
+/// large.value().unwrap();
+/// 
+/// // "large" is flushed anyway because it frees more memory
+/// pool.handle().free(1).unwrap();
+/// 
+/// assert!(small.is_hot());
+/// assert!(large.is_cold());
+/// ```
+pub struct SwapGreedyDualSizeManager {
+    /// Inflation value `L` in fixed point
+    inflation: AtomicU64,
+
+    entries: InplaceCell<HashMap<u64, SwapGreedyDualEntry>>,
+
+    /// Reload cost of the entity with given size
+    cost: Box<dyn Fn(usize) -> u64 + Send + Sync>
+}
+
+impl Default for SwapGreedyDualSizeManager {
+    #[inline]
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl SwapGreedyDualSizeManager {
+    /// Fixed point scale of the ranks
+    const SCALE: u128 = 1 << 32;
+
+    #[inline]
+    pub fn new(thread_safe: bool) -> Self {
+        Self {
+            inflation: AtomicU64::new(0),
+            entries: InplaceCell::new(HashMap::new(), thread_safe),
+            cost: Box::new(|_| 1)
+        }
+    }
+
+    #[inline]
+    /// Change entities' reload cost
+    /// 
+    /// The function receives entity's size and returns
+    /// the cost of reading it from the storage. Units don't
+    /// matter, only costs relative to each other do
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// // Entities are read from a slow disk, and reading
+    /// // takes 10ms plus 1ms per each 64 KB of data
+    /// let manager = SwapGreedyDualSizeManager::default()
+    ///     .with_cost(|size| 10 + size as u64 / 65536);
+    /// ```
+    pub fn with_cost(self, cost: impl Fn(usize) -> u64 + Send + Sync + 'static) -> Self {
+        Self {
+            cost: Box::new(cost),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Calculate new rank of the entity
+    fn rerank(&self, entry: &mut SwapGreedyDualEntry) -> u64 {
+        let size = entry.size.max(1);
+
+        let credit = u128::from((self.cost)(size))
+            .saturating_mul(u128::from(entry.hits.max(1)))
+            .saturating_mul(Self::SCALE) / size as u128;
+
+        let credit = u64::try_from(credit).unwrap_or(u64::MAX);
+
+        entry.rank = self.inflation.load(Ordering::Acquire).saturating_add(credit);
+
+        entry.rank
+    }
+
+    #[inline]
+    fn update(&self, uuid: u64, updater: impl FnOnce(&mut SwapGreedyDualEntry)) -> u64 {
+        let mut entries = self.entries.get_mut();
+
+        let entry = entries.entry(uuid).or_default();
+
+        updater(entry);
+
+        self.rerank(entry)
+    }
+}
+
+impl SwapManager for SwapGreedyDualSizeManager {
+    #[inline]
+    fn upgrade(&self, uuid: u64) -> u64 {
+        self.update(uuid, |entry| entry.hits += 1)
+    }
+
+    #[inline]
+    fn rank(&self, uuid: u64) -> u64 {
+        self.entries.get_ref()
+            .get(&uuid)
+            .map(|entry| entry.rank)
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn on_spawn(&self, uuid: u64, size: usize) {
+        self.update(uuid, |entry| entry.size = size);
+    }
+
+    #[inline]
+    fn on_load(&self, uuid: u64, size: usize) {
+        self.update(uuid, |entry| entry.size = size);
+    }
+
+    #[inline]
+    fn on_resize(&self, uuid: u64, size: usize) {
+        self.update(uuid, |entry| entry.size = size);
+    }
+
+    #[inline]
+    fn on_flush(&self, uuid: u64) {
+        self.entries.update(|entries| {
+            if let Some(entry) = entries.get_mut(&uuid) {
+                entry.hits = 0;
+            }
+        });
+    }
+
+    #[inline]
+    fn on_remove(&self, uuid: u64) {
+        self.entries.update(|entries| {
+            entries.remove(&uuid);
+        });
+    }
+
+    fn select_victims(&self, memory: usize, candidates: &dyn SwapCandidates) -> Vec<u64> {
+        let mut freed = 0;
+        let mut inflation = 0;
+
+        let victims = candidates.iter()
+            .take_while(|candidate| {
+                let needed = freed < memory;
+
+                freed += candidate.size;
+
+                needed
+            })
+            .map(|candidate| {
+                inflation = candidate.rank;
+
+                candidate.uuid
+            })
+            .collect();
+
+        // Age remaining entities by raising the rank
+        // given to the entities used from now on
+        self.inflation.fetch_max(inflation, Ordering::AcqRel);
+
+        victims
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SwapArcList {
+    /// Entities used once since they were loaded
+    Recent,
+
+    /// Entities used at least twice since they were loaded
+    Frequent,
+
+    /// Ghosts of the flushed recent entities
+    RecentGhost,
+
+    /// Ghosts of the flushed frequent entities
+    FrequentGhost
+}
+
+impl SwapArcList {
+    #[inline]
+    fn index(&self) -> usize {
+        match self {
+            Self::Recent        => 0,
+            Self::Frequent      => 1,
+            Self::RecentGhost   => 2,
+            Self::FrequentGhost => 3
+        }
+    }
+}
+
+#[derive(Default)]
+struct SwapArcState {
+    counter: u64,
+
+    /// Target amount of the recent entities
+    target: usize,
+
+    /// Entities' uuids ordered from the least recently used
+    lists: [BTreeMap<u64, u64>; 4],
+
+    /// Lists and positions of the entities
+    entries: HashMap<u64, (SwapArcList, u64)>,
+
+    /// Entities which were just spawned and their hotness
+    /// 
+    /// Spawned entities are upgraded by the pool,
+    /// which must not be counted as their use
+    spawned: HashMap<u64, bool>,
+
+    /// Recent entities which were loaded but not used yet
+    unused: HashSet<u64>
+}
+
+impl SwapArcState {
+    #[inline]
+    fn len(&self, list: SwapArcList) -> usize {
+        self.lists[list.index()].len()
+    }
+
+    #[inline]
+    fn remove(&mut self, uuid: u64) -> Option<SwapArcList> {
+        self.unused.remove(&uuid);
+
+        let (list, position) = self.entries.remove(&uuid)?;
+
+        self.lists[list.index()].remove(&position);
+
+        Some(list)
+    }
+
+    /// Move entity to the most recently used position of the list
+    #[inline]
+    fn push(&mut self, uuid: u64, list: SwapArcList) -> u64 {
+        self.remove(uuid);
+
+        self.counter += 1;
+
+        self.lists[list.index()].insert(self.counter, uuid);
+        self.entries.insert(uuid, (list, self.counter));
+
+        self.counter
+    }
+
+    /// Remove the oldest ghosts so there's
+    /// no more ghosts than hot entities
+    fn trim_ghosts(&mut self) {
+        let hot = self.len(SwapArcList::Recent) + self.len(SwapArcList::Frequent);
+
+        while self.len(SwapArcList::RecentGhost) + self.len(SwapArcList::FrequentGhost) > hot {
+            let list = if self.len(SwapArcList::RecentGhost) > self.len(SwapArcList::FrequentGhost) {
+                SwapArcList::RecentGhost
+            } else {
+                SwapArcList::FrequentGhost
+            };
+
+            let Some((_, uuid)) = self.lists[list.index()].pop_first() else {
+                break;
+            };
+
+            self.entries.remove(&uuid);
+        }
+    }
+}
+
+/// Adaptive replacement cache manager
+/// 
+/// Entities are split into recently used (used once since
+/// they were loaded) and frequently used (used at least twice)
+/// ones. The manager remembers ghosts of the flushed entities,
+/// and uses them to adapt the target amount of the recent
+/// entities: if flushed recent entities are used again, the
+/// recent list grows, and if frequent ones are - it shrinks
+/// 
+/// This makes the manager resistant to scans: entities used
+/// once don't flush entities which are used frequently
+/// 
+/// ```rust
+/// use swap_pool::prelude::*;
+/// 
+/// let mut pool = SwapPoolBuilder::default()
+///     .with_manager(SwapArcManager::default())
+///     .with_storage(SwapMemoryStorage::default())
+///     .build(1024, "");
+/// 
+/// let hot = pool.spawn_named("hot", vec![0; 128]).unwrap();
+/// 
+/// // Use the entity frequently
+/// hot.value().unwrap();
+/// hot.value().unwrap();
+/// 
+/// let entities = (0..32)
+///     .map(|i| pool.spawn_named(format!("scan {i}"), vec![1; 128]).unwrap())
+///     .collect::<Vec<_>>();
+/// 
+/// // Scan through lots of other entities
+/// for entity in &entities {
+///     entity.value().unwrap();
+/// }
+/// 
+/// // Frequently used entity is still hot
+/// assert!(hot.is_hot());
+/// ```
+pub struct SwapArcManager {
+    state: InplaceCell<SwapArcState>
+}
+
+impl Default for SwapArcManager {
+    #[inline]
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl SwapArcManager {
+    #[inline]
+    pub fn new(thread_safe: bool) -> Self {
+        Self {
+            state: InplaceCell::new(SwapArcState::default(), thread_safe)
+        }
+    }
+
+    #[inline]
+    /// Get target amount of the recently used entities
+    pub fn target(&self) -> usize {
+        self.state.get_ref().target
+    }
+}
+
+impl SwapManager for SwapArcManager {
+    fn upgrade(&self, uuid: u64) -> u64 {
+        let mut state = self.state.get_mut();
+
+        let list = state.entries.get(&uuid).map(|(list, _)| *list);
+
+        let recent_ghosts = state.len(SwapArcList::RecentGhost);
+        let frequent_ghosts = state.len(SwapArcList::FrequentGhost);
+
+        // Don't count the upgrade made by the pool when spawning the entity
+        if let Some(hot) = state.spawned.remove(&uuid) {
+            if !hot {
+                return 0;
+            }
+
+            let rank = state.push(uuid, SwapArcList::Recent);
+
+            state.unused.insert(uuid);
+
+            return rank;
+        }
+
+        let list = match list {
+            // First use of the loaded entity
+            Some(SwapArcList::Recent) if state.unused.contains(&uuid) => SwapArcList::Recent,
+
+            Some(SwapArcList::Recent | SwapArcList::Frequent) => SwapArcList::Frequent,
+
+            // Recent entity was flushed too early
+            Some(SwapArcList::RecentGhost) => {
+                let hot = state.len(SwapArcList::Recent) + state.len(SwapArcList::Frequent);
+                let delta = (frequent_ghosts / recent_ghosts.max(1)).max(1);
+
+                state.target = (state.target + delta).min(hot.max(1));
+
+                SwapArcList::Frequent
+            }
+
+            // Frequent entity was flushed too early
+            Some(SwapArcList::FrequentGhost) => {
+                let delta = (recent_ghosts / frequent_ghosts.max(1)).max(1);
+
+                state.target = state.target.saturating_sub(delta);
+
+                SwapArcList::Frequent
+            }
+
+            None => SwapArcList::Recent
+        };
+
+        state.push(uuid, list)
+    }
+
+    #[inline]
+    fn rank(&self, uuid: u64) -> u64 {
+        self.state.get_ref()
+            .entries
+            .get(&uuid)
+            .map(|(_, position)| *position)
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn on_spawn(&self, uuid: u64, size: usize) {
+        self.state.update(|state| {
+            state.spawned.insert(uuid, size > 0);
+        });
+    }
+
+    #[inline]
+    fn on_load(&self, uuid: u64, _size: usize) {
+        self.state.update(|state| {
+            let list = state.entries.get(&uuid).map(|(list, _)| *list);
+
+            // Entity was loaded without being used
+            if !matches!(list, Some(SwapArcList::Recent | SwapArcList::Frequent)) {
+                state.push(uuid, SwapArcList::Recent);
+                state.unused.insert(uuid);
+            }
+        });
+    }
+
+    #[inline]
+    fn on_flush(&self, uuid: u64) {
+        self.state.update(|state| {
+            match state.entries.get(&uuid).map(|(list, _)| *list) {
+                Some(SwapArcList::Recent) => {
+                    state.push(uuid, SwapArcList::RecentGhost);
+                }
+
+                Some(SwapArcList::Frequent) => {
+                    state.push(uuid, SwapArcList::FrequentGhost);
+                }
+
+                _ => ()
+            }
+
+            state.trim_ghosts();
+        });
+    }
+
+    #[inline]
+    fn on_remove(&self, uuid: u64) {
+        self.state.update(|state| {
+            state.remove(uuid);
+            state.spawned.remove(&uuid);
+        });
+    }
+
+    fn select_victims(&self, memory: usize, candidates: &dyn SwapCandidates) -> Vec<u64> {
+        let state = self.state.get_ref();
+
+        // Hot entities from the least recently used
+        let mut recent = state.lists[SwapArcList::Recent.index()].values()
+            .flat_map(|uuid| candidates.get(*uuid))
+            .peekable();
+
+        let mut frequent = state.lists[SwapArcList::Frequent.index()].values()
+            .flat_map(|uuid| candidates.get(*uuid))
+            .peekable();
+
+        let mut recent_len = state.len(SwapArcList::Recent);
+
+        let mut victims = Vec::new();
+        let mut freed = 0;
+
+        while freed < memory {
+            // Flush recent entities if there's more than the target amount
+            let candidate = if recent_len > state.target || frequent.peek().is_none() {
+                recent.next().or_else(|| frequent.next())
+            } else {
+                frequent.next().or_else(|| recent.next())
+            };
+
+            let Some(candidate) = candidate else {
+                break;
+            };
+
+            if state.entries.get(&candidate.uuid).map(|(list, _)| *list) == Some(SwapArcList::Recent) {
+                recent_len = recent_len.saturating_sub(1);
+            }
+
+            freed += candidate.size;
+
+            victims.push(candidate.uuid);
+        }
+
+        // Hot entities which are not listed by the manager
+        if freed < memory {
+            victims.extend(candidates.iter()
+                .filter(|candidate| {
+                    let list = state.entries.get(&candidate.uuid).map(|(list, _)| *list);
+
+                    !matches!(list, Some(SwapArcList::Recent | SwapArcList::Frequent))
+                })
+                .map(|candidate| candidate.uuid));
+        }
+
+        victims
+    }
+}
+
+struct SwapClockSlot {
+    uuid: u64,
+    referenced: AtomicBool,
+
+    /// Entity was just spawned, so the
+    /// following upgrade is made by the pool
+    spawned: AtomicBool
+}
+
+#[derive(Default)]
+struct SwapClockState {
+    /// Hot entities and their reference bits
+    ring: Vec<SwapClockSlot>,
+
+    /// Positions of the entities in the ring
+    positions: HashMap<u64, usize>
+}
+
+impl SwapClockState {
+    #[inline]
+    fn insert(&mut self, uuid: u64, spawned: bool) {
+        if !self.positions.contains_key(&uuid) {
+            self.positions.insert(uuid, self.ring.len());
+
+            self.ring.push(SwapClockSlot {
+                uuid,
+                referenced: AtomicBool::new(false),
+                spawned: AtomicBool::new(spawned)
+            });
+        }
+    }
+
+    #[inline]
+    fn remove(&mut self, uuid: u64) {
+        if let Some(position) = self.positions.remove(&uuid) {
+            self.ring.swap_remove(position);
+
+            // Update position of the moved entity
+            if let Some(moved) = self.ring.get(position) {
+                self.positions.insert(moved.uuid, position);
+            }
+        }
+    }
+}
+
+/// CLOCK (second chance) manager
+/// 
+/// Hot entities are stored in a ring with a reference bit
+/// which is set when the entity is used. When the pool needs
+/// some memory, the manager sweeps the ring, clearing set bits
+/// and flushing entities which weren't used since the last sweep
+/// 
+/// Upgrades only set the reference bit, without locking
+/// the manager for writing, which makes this manager cheap
+/// for frequently read entities. The manager doesn't rank
+/// entities, so their ranks are always zero
+/// 
+/// ```rust
+/// use swap_pool::prelude::*;
+/// 
+/// let mut pool = SwapPoolBuilder::default()
+///     .with_manager(SwapClockManager::default())
+///     .with_storage(SwapMemoryStorage::default())
+///     .build(1024, "");
+/// 
+/// let a = pool.spawn_named("a", vec![0; 128]).unwrap();
+/// let b = pool.spawn_named("b", vec![1; 128]).unwrap();
+/// 
+/// // Set reference bit of "a"
+/// a.value().unwrap();
+/// 
+/// // "a" gets the second chance
+/// pool.handle().free(1).unwrap();
+/// 
+/// assert!(a.is_hot());
+/// assert!(b.is_cold());
+/// ```
+pub struct SwapClockManager {
+    state: InplaceCell<SwapClockState>,
+    hand: AtomicUsize
+}
+
+impl Default for SwapClockManager {
+    #[inline]
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl SwapClockManager {
+    #[inline]
+    pub fn new(thread_safe: bool) -> Self {
+        Self {
+            state: InplaceCell::new(SwapClockState::default(), thread_safe),
+            hand: AtomicUsize::new(0)
+        }
+    }
+}
+
+impl SwapManager for SwapClockManager {
+    #[inline]
+    fn upgrade(&self, uuid: u64) -> u64 {
+        let state = self.state.get_ref();
+
+        if let Some(position) = state.positions.get(&uuid) {
+            let slot = &state.ring[*position];
+
+            if !slot.spawned.swap(false, Ordering::AcqRel) {
+                slot.referenced.store(true, Ordering::Release);
+            }
+        }
+
+        0
+    }
+
+    #[inline]
+    fn rank(&self, _uuid: u64) -> u64 {
+        0
+    }
+
+    #[inline]
+    fn on_spawn(&self, uuid: u64, size: usize) {
+        if size > 0 {
+            self.state.update(|state| state.insert(uuid, true));
+        }
+    }
+
+    #[inline]
+    fn on_load(&self, uuid: u64, _size: usize) {
+        self.state.update(|state| state.insert(uuid, false));
+    }
+
+    #[inline]
+    fn on_flush(&self, uuid: u64) {
+        self.state.update(|state| state.remove(uuid));
+    }
+
+    #[inline]
+    fn on_remove(&self, uuid: u64) {
+        self.state.update(|state| state.remove(uuid));
+    }
+
+    fn select_victims(&self, memory: usize, candidates: &dyn SwapCandidates) -> Vec<u64> {
+        let state = self.state.get_ref();
+
+        let len = state.ring.len();
+
+        let mut victims = Vec::new();
+        let mut freed = 0;
+
+        // All the bits are cleared after the first round,
+        // so two rounds are enough to visit every entity
+        let mut hand = self.hand.load(Ordering::Acquire);
+
+        for _ in 0..len * 2 {
+            if freed >= memory {
+                break;
+            }
+
+            hand %= len;
+
+            let slot = &state.ring[hand];
+
+            hand += 1;
+
+            // Skip pinned or already flushed entities
+            let Some(candidate) = candidates.get(slot.uuid) else {
+                continue;
+            };
+
+            if victims.contains(&slot.uuid) {
+                continue;
+            }
+
+            // Give the entity a second chance
+            if slot.referenced.swap(false, Ordering::AcqRel) {
+                continue;
+            }
+
+            freed += candidate.size;
+
+            victims.push(slot.uuid);
+        }
+
+        self.hand.store(hand, Ordering::Release);
+
+        // Hot entities which are not listed by the manager
+        if freed < memory {
+            victims.extend(candidates.iter()
+                .filter(|candidate| !state.positions.contains_key(&candidate.uuid))
+                .map(|candidate| candidate.uuid));
+        }
+
+        victims
+    }
 }
@@ -0,0 +1,154 @@
+use super::error::{SwapResult, SwapError};
+
+/// Storage key used to save persistent pools' manifests
+pub const SWAP_MANIFEST_KEY: &str = ".swap-manifest";
+
+const SWAP_MANIFEST_HEADER: &str = "swap-pool manifest v1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapManifestEntry {
+    /// Entity's unique id
+    pub uuid: u64,
+
+    /// Entity's swap storage key
+    pub key: String,
+
+    /// Size of the stored value in bytes
+    pub size: usize
+}
+
+/// Persistent swap pools' manifest
+/// 
+/// Manifest lists entities stored in the pool's storage
+/// so they can be restored after the process restart
+/// 
+/// ```rust
+/// use swap_pool::prelude::*;
+/// 
+/// let manifest = SwapManifest {
+///     allocated: 1024,
+///     transformer: String::from("identity"),
+///     entities: vec![
+///         SwapManifestEntry {
+///             uuid: 123,
+///             key: String::from("entity with spaces"),
+///             size: 64
+///         }
+///     ]
+/// };
+/// 
+/// assert_eq!(SwapManifest::from_bytes(&manifest.to_bytes()).unwrap(), manifest);
+/// 
+/// assert!(matches!(SwapManifest::from_bytes(b"invalid"), Err(SwapError::Manifest(_))));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapManifest {
+    /// Maximum amount of memory which can be allocated by the pool items
+    pub allocated: usize,
+
+    /// Id of the transformer used to write stored values
+    pub transformer: String,
+
+    pub entities: Vec<SwapManifestEntry>
+}
+
+impl SwapManifest {
+    /// Serialize manifest to bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut manifest = format!("{SWAP_MANIFEST_HEADER}\n");
+
+        manifest += &format!("allocated {}\n", self.allocated);
+        manifest += &format!("transformer {}\n", encode_hex(self.transformer.as_bytes()));
+
+        for entity in &self.entities {
+            manifest += &format!("entity {:x} {} {}\n", entity.uuid, entity.size, encode_hex(entity.key.as_bytes()));
+        }
+
+        manifest.into_bytes()
+    }
+
+    /// Deserialize manifest from bytes
+    pub fn from_bytes(bytes: &[u8]) -> SwapResult<Self> {
+        let manifest = std::str::from_utf8(bytes)
+            .map_err(|_| SwapError::Manifest(String::from("manifest is not a valid utf-8 string")))?;
+
+        let mut lines = manifest.lines();
+
+        if lines.next() != Some(SWAP_MANIFEST_HEADER) {
+            return Err(SwapError::Manifest(String::from("unsupported manifest header")));
+        }
+
+        let mut allocated = None;
+        let mut transformer = None;
+        let mut entities = Vec::new();
+
+        for line in lines {
+            let invalid = || SwapError::Manifest(format!("invalid manifest line: {line}"));
+
+            let mut words = line.split(' ');
+
+            match words.next() {
+                Some("allocated") => {
+                    allocated = Some(words.next()
+                        .and_then(|allocated| allocated.parse::<usize>().ok())
+                        .ok_or_else(invalid)?);
+                }
+
+                Some("transformer") => {
+                    transformer = Some(words.next()
+                        .and_then(decode_hex_string)
+                        .ok_or_else(invalid)?);
+                }
+
+                Some("entity") => {
+                    let uuid = words.next()
+                        .and_then(|uuid| u64::from_str_radix(uuid, 16).ok())
+                        .ok_or_else(invalid)?;
+
+                    let size = words.next()
+                        .and_then(|size| size.parse::<usize>().ok())
+                        .ok_or_else(invalid)?;
+
+                    let key = words.next()
+                        .and_then(decode_hex_string)
+                        .ok_or_else(invalid)?;
+
+                    entities.push(SwapManifestEntry {
+                        uuid,
+                        key,
+                        size
+                    });
+                }
+
+                Some("") => continue,
+
+                _ => return Err(invalid())
+            }
+        }
+
+        Ok(Self {
+            allocated: allocated.ok_or_else(|| SwapError::Manifest(String::from("allocated memory is not specified")))?,
+            transformer: transformer.ok_or_else(|| SwapError::Manifest(String::from("transformer is not specified")))?,
+            entities
+        })
+    }
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub(crate) fn decode_hex_string(hex: &str) -> Option<String> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect::<Option<Vec<u8>>>()?;
+
+    String::from_utf8(bytes).ok()
+}
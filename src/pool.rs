@@ -1,19 +1,45 @@
 use std::path::PathBuf;
+use std::time::Duration;
 use std::sync::Arc;
 use std::hash::Hash;
 
 use super::size::SizeOf;
 use super::uuid;
-use super::error::SwapResult;
+use super::error::{SwapResult, SwapError};
 use super::entity::SwapEntity;
 use super::handle::SwapHandle;
 use super::manager::{SwapManager, SwapLastUseManager};
 use super::transformer::{SwapTransformer, SwapIdentityTransformer};
+use super::storage::{SwapStorage, SwapFsStorage};
+use super::manifest::{SwapManifest, SWAP_MANIFEST_KEY};
+use super::priority::SwapPriority;
+
+#[inline]
+/// Check that the entity's name doesn't collide
+/// with the pool's manifest or alive entities
+fn check_name<T>(handle: &SwapHandle<T>, name: &str) -> SwapResult<()> {
+    if name == SWAP_MANIFEST_KEY {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "entity name is reserved by the swap pool manifest").into());
+    }
+
+    if handle.is_key_used(name) {
+        return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "entity name is used by another entity").into());
+    }
+
+    Ok(())
+}
+
+/// Reopened swap pool and its restored entities
+pub type SwapRestoredPool<T> = (SwapPool<T>, Vec<Arc<SwapEntity<T>>>);
 
 pub struct SwapPoolBuilder {
     thread_safe: bool,
     manager: Box<dyn SwapManager>,
-    transformer: Box<dyn SwapTransformer>
+    transformer: Box<dyn SwapTransformer>,
+    storage: Option<Box<dyn SwapStorage>>,
+    keep_swap: bool,
+    persistent: bool,
+    flusher: Option<(usize, usize)>
 }
 
 impl Default for SwapPoolBuilder {
@@ -22,7 +48,11 @@ impl Default for SwapPoolBuilder {
         Self {
             thread_safe: true,
             manager: Box::<SwapLastUseManager>::default(),
-            transformer: Box::new(SwapIdentityTransformer)
+            transformer: Box::new(SwapIdentityTransformer),
+            storage: None,
+            keep_swap: false,
+            persistent: false,
+            flusher: None
         }
     }
 }
@@ -31,12 +61,30 @@ impl SwapPoolBuilder {
     #[inline]
     /// Change swap pool thread safety
     /// 
+    /// The pool is always `Send + Sync` (if its values are),
+    /// but non thread safe pools can lose parallel updates.
     /// See `InplaceCell` docs for details
+    /// 
+    /// ```rust,no_run
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_thread_safe(true)
+    ///     .build(128, "/tmp");
+    /// 
+    /// let entity = pool.spawn(vec![0; 128]).unwrap();
+    /// 
+    /// // Read the entity from multiple threads
+    /// std::thread::scope(|scope| {
+    ///     for _ in 0..4 {
+    ///         scope.spawn(|| entity.value().unwrap());
+    ///     }
+    /// });
+    /// ```
     pub fn with_thread_safe(self, thread_safe: bool) -> Self {
         Self {
             thread_safe,
-            manager: self.manager,
-            transformer: self.transformer
+            ..self
         }
     }
 
@@ -44,9 +92,8 @@ impl SwapPoolBuilder {
     /// Change default swap pool entities manager
     pub fn with_manager(self, manager: impl SwapManager + 'static) -> Self {
         Self {
-            thread_safe: self.thread_safe,
             manager: Box::new(manager),
-            transformer: self.transformer
+            ..self
         }
     }
 
@@ -54,26 +101,302 @@ impl SwapPoolBuilder {
     /// Change default swap pool entities' values transformer
     pub fn with_transformer(self, transformer: impl SwapTransformer + 'static) -> Self {
         Self {
-            thread_safe: self.thread_safe,
-            manager: self.manager,
-            transformer: Box::new(transformer)
+            transformer: Box::new(transformer),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Change default swap pool storage
+    /// 
+    /// By default `SwapFsStorage` is used which stores
+    /// every entity in its own file in the pool's folder
+    pub fn with_storage(self, storage: impl SwapStorage + 'static) -> Self {
+        Self {
+            storage: Some(Box::new(storage)),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Change swap copies keeping mode
+    /// 
+    /// By default swap copies are removed when entities become hot,
+    /// which saves storage space. When enabled, swap copies of the
+    /// entities are kept in the storage after they were loaded to
+    /// the RAM, so clean entities can be flushed without writing anything
+    pub fn with_keep_swap(self, keep_swap: bool) -> Self {
+        Self {
+            keep_swap,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Change swap pool persistence
+    /// 
+    /// Persistent pools save their manifest to the storage
+    /// on `flush` and when the pool is dropped, so they can
+    /// be reopened with `SwapPool::open`. Detach entities
+    /// to keep their values in the storage after dropping
+    /// 
+    /// The pool's transformer must have an id (see `SwapTransformer::id`),
+    /// otherwise saving the manifest fails with `SwapError::Manifest`.
+    /// The storage must keep its values after it's dropped.
+    /// Use `SwapArenaStorage::open` instead of `SwapArenaStorage::new`
+    /// to store persistent pools in a single swap file
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let folder = std::env::temp_dir().join("swap-pool-persistent");
+    /// 
+    /// # let _ = std::fs::remove_dir_all(&folder);
+    /// std::fs::create_dir_all(&folder).unwrap();
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_persistent(true)
+    ///     .build(1024, &folder);
+    /// 
+    /// let entity = pool.spawn_named("entity", vec![0; 128]).unwrap();
+    /// let temp = pool.spawn_named("temp", vec![1; 128]).unwrap();
+    /// 
+    /// // Keep the entity's value in the storage after dropping
+    /// entity.detach();
+    /// 
+    /// drop(entity);
+    /// drop(temp);
+    /// drop(pool);
+    /// 
+    /// // Reopen the pool
+    /// let (pool, entities) = SwapPool::<Vec<u8>>::open(&folder).unwrap();
+    /// 
+    /// // Attached entities remove their values when dropped
+    /// assert_eq!(entities.len(), 1);
+    /// assert_eq!(entities[0].key(), "entity");
+    /// assert_eq!(entities[0].value().unwrap(), vec![0; 128]);
+    /// # drop(entities);
+    /// # drop(pool);
+    /// # std::fs::remove_dir_all(&folder).unwrap();
+    /// ```
+    pub fn with_persistent(self, persistent: bool) -> Self {
+        Self {
+            persistent,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set background flusher's high and low watermarks
+    /// 
+    /// When the pool's used memory exceeds the high watermark,
+    /// the flusher flushes low-ranked entities in a background
+    /// thread until it drops to the low watermark, so foreground
+    /// reads rarely have to wait for the storage.
+    /// 
+    /// The flusher requires values to be `Send + Sync`, so the pool
+    /// must be built by `build_with_flusher`. Pools built by `build`
+    /// or reopened by `open` ignore the watermarks until
+    /// `SwapHandle::start_flusher` is called
+    /// 
+    /// ```rust
+    /// use std::time::{Duration, Instant};
+    /// 
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .with_flusher(2048, 1024)
+    ///     .build_with_flusher(4096, "")
+    ///     .unwrap();
+    /// 
+    /// let entities = (0..32)
+    ///     .map(|i| pool.spawn_named(format!("entity {i}"), vec![0; 128]).unwrap())
+    ///     .collect::<Vec<_>>();
+    /// 
+    /// // Wait until the flusher frees the memory
+    /// let deadline = Instant::now() + Duration::from_secs(10);
+    /// 
+    /// while pool.handle().used() > 1024 {
+    ///     assert!(Instant::now() < deadline, "flusher didn't free the memory");
+    /// 
+    ///     std::thread::sleep(Duration::from_millis(10));
+    /// }
+    /// 
+    /// assert!(entities.iter().any(|entity| entity.is_cold()));
+    /// ```
+    pub fn with_flusher(self, high: usize, low: usize) -> Self {
+        Self {
+            flusher: Some((high, low)),
+            ..self
         }
     }
 
     #[inline]
     /// Build swap pool
+    /// 
+    /// Given folder is used by the default `SwapFsStorage`
+    /// and is ignored if a custom storage is set
     pub fn build<T>(self, allocated: usize, folder: impl Into<PathBuf>) -> SwapPool<T> {
+        let storage = self.storage.unwrap_or_else(|| Box::new(SwapFsStorage::new(folder)));
+
+        let mut handle = SwapHandle::new(allocated, self.manager, self.transformer, storage, self.thread_safe)
+            .with_keep_swap(self.keep_swap)
+            .with_persistent(self.persistent);
+
+        if let Some((high, low)) = self.flusher {
+            handle = handle.with_flusher(high, low);
+        }
+
         SwapPool {
-            handle: Arc::new(SwapHandle::new(allocated, self.manager, self.transformer, self.thread_safe)),
-            folder: folder.into(),
+            handle: Arc::new(handle),
             thread_safe: self.thread_safe
         }
     }
+
+    #[inline]
+    /// Build swap pool and start its background flusher
+    /// 
+    /// See `SwapPoolBuilder::with_flusher` and `SwapHandle::start_flusher`
+    /// 
+    /// Return an error if the flusher's watermarks are not set
+    /// or the flusher thread can't be spawned
+    pub fn build_with_flusher<T>(self, allocated: usize, folder: impl Into<PathBuf>) -> std::io::Result<SwapPool<T>>
+    where
+        T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf + Send + Sync + 'static,
+        <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+        <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+    {
+        let pool = self.build(allocated, folder);
+
+        pool.handle.start_flusher()?;
+
+        Ok(pool)
+    }
+}
+
+impl SwapPoolBuilder {
+    /// Reopen persistent swap pool using its manifest
+    /// 
+    /// Return the pool and all the entities listed in the manifest.
+    /// Restored entities are cold and detached, so their values
+    /// are kept after dropping. Attach them to remove their swap
+    /// 
+    /// Given folder is used by the default `SwapFsStorage`
+    /// and is ignored if a custom storage is set
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// struct ReverseTransformer;
+    /// 
+    /// impl SwapTransformer for ReverseTransformer {
+    ///     fn forward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    ///         Ok(data.into_iter().rev().collect())
+    ///     }
+    /// 
+    ///     fn backward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    ///         Ok(data.into_iter().rev().collect())
+    ///     }
+    /// }
+    /// 
+    /// let folder = std::env::temp_dir().join("swap-pool-open");
+    /// 
+    /// # let _ = std::fs::remove_dir_all(&folder);
+    /// std::fs::create_dir_all(&folder).unwrap();
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_persistent(true)
+    ///     .build(1024, &folder);
+    /// 
+    /// let entity = pool.spawn_named("entity", vec![1, 2, 3]).unwrap();
+    /// 
+    /// entity.detach();
+    /// 
+    /// drop(entity);
+    /// drop(pool);
+    /// 
+    /// // Stored values can't be read by a transformer without id
+    /// let result = SwapPoolBuilder::default()
+    ///     .with_transformer(ReverseTransformer)
+    ///     .open::<Vec<u8>>(&folder);
+    /// 
+    /// assert!(matches!(result, Err(SwapError::Manifest(_))));
+    /// 
+    /// // Restored entities are kept after dropping
+    /// for _ in 0..2 {
+    ///     let (pool, entities) = SwapPool::<Vec<u8>>::open(&folder).unwrap();
+    /// 
+    ///     assert_eq!(entities.len(), 1);
+    ///     assert!(entities[0].is_detached());
+    ///     assert_eq!(entities[0].value().unwrap(), vec![1, 2, 3]);
+    /// 
+    ///     drop(entities);
+    ///     drop(pool);
+    /// }
+    /// 
+    /// // Stored values must match the manifest
+    /// std::fs::write(folder.join("entity"), [0; 16]).unwrap();
+    /// 
+    /// let result = SwapPool::<Vec<u8>>::open(&folder);
+    /// 
+    /// assert!(matches!(result, Err(SwapError::Manifest(_))));
+    /// # std::fs::remove_dir_all(&folder).unwrap();
+    /// ```
+    pub fn open<T>(self, folder: impl Into<PathBuf>) -> SwapResult<SwapRestoredPool<T>>
+    where
+        T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
+        <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+        <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
+    {
+        let Some(transformer) = self.transformer.id() else {
+            return Err(SwapError::Manifest(String::from("pool's transformer has no id")));
+        };
+
+        let storage = self.storage.unwrap_or_else(|| Box::new(SwapFsStorage::new(folder)));
+
+        let manifest = SwapManifest::from_bytes(&storage.read(SWAP_MANIFEST_KEY)?)?;
+
+        if manifest.transformer != transformer {
+            return Err(SwapError::Manifest(format!(
+                "pool was saved with transformer {}, but {transformer} is used",
+                manifest.transformer
+            )));
+        }
+
+        for entity in &manifest.entities {
+            if !storage.exists(&entity.key) || storage.size(&entity.key)? != entity.size {
+                return Err(SwapError::Manifest(format!("stored value of entity {} is missing or corrupted", entity.key)));
+            }
+        }
+
+        let mut handle = SwapHandle::new(manifest.allocated, self.manager, self.transformer, storage, self.thread_safe)
+            .with_keep_swap(self.keep_swap)
+            .with_persistent(true);
+
+        if let Some((high, low)) = self.flusher {
+            handle = handle.with_flusher(high, low);
+        }
+
+        let pool = SwapPool {
+            handle: Arc::new(handle),
+            thread_safe: self.thread_safe
+        };
+
+        let entities = manifest.entities.into_iter()
+            .map(|entity| {
+                let entity = SwapEntity::restore(pool.handle.clone(), entity.key, entity.uuid, pool.thread_safe);
+
+                pool.handle.push_entity(entity)
+            })
+            .collect();
+
+        Ok((pool, entities))
+    }
 }
 
 pub struct SwapPool<T> {
     handle: Arc<SwapHandle<T>>,
-    folder: PathBuf,
     thread_safe: bool
 }
 
@@ -139,12 +462,23 @@ impl<T> SwapPool<T> {
 impl<T> SwapPool<T>
 where
     T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf,
-    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + 'static,
-    <T as TryInto<Vec<u8>>>::Error: std::error::Error + 'static
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
 {
+    #[inline]
+    /// Reopen persistent swap pool stored in the given folder
+    /// 
+    /// See `SwapPoolBuilder::open` for details
+    pub fn open(folder: impl Into<PathBuf>) -> SwapResult<SwapRestoredPool<T>> {
+        SwapPoolBuilder::default().open(folder)
+    }
+
     #[inline]
     /// Spawn new entity in the swap pool with a given file name
     /// 
+    /// Name of the pool's manifest (`SWAP_MANIFEST_KEY`) can't be used,
+    /// as well as names of the alive entities (see `SwapHandle::is_key_used`)
+    /// 
     /// ```rust,no_run
     /// use swap_pool::prelude::*;
     /// 
@@ -166,9 +500,47 @@ where
     /// assert!(!std::path::PathBuf::from("/tmp/My cool swap file").exists());
     /// ```
     pub fn spawn_named(&mut self, name: impl AsRef<str>, value: T) -> SwapResult<Arc<SwapEntity<T>>> {
-        let path = self.folder.join(name.as_ref());
+        self.spawn_named_with_priority(name, value, SwapPriority::default())
+    }
+
+    #[inline]
+    /// Spawn new entity in the swap pool with a given file name and eviction class
+    /// 
+    /// See `SwapEntity::set_priority`
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let mut pool = SwapPoolBuilder::default()
+    ///     .with_storage(SwapMemoryStorage::default())
+    ///     .build(1024, "");
+    /// 
+    /// // Bulk data should be flushed first
+    /// let entity = pool.spawn_named_with_priority("data", vec![0; 128], SwapPriority::Low).unwrap();
+    /// 
+    /// assert_eq!(entity.priority(), SwapPriority::Low);
+    /// ```
+    pub fn spawn_named_with_priority(&mut self, name: impl AsRef<str>, value: T, priority: SwapPriority) -> SwapResult<Arc<SwapEntity<T>>> {
+        check_name(&self.handle, name.as_ref())?;
+
+        let entity = SwapEntity::create(value, self.handle.clone(), name.as_ref(), self.thread_safe)?;
+
+        entity.set_priority(priority);
+
+        Ok(self.handle.push_entity(entity))
+    }
+
+    #[inline]
+    /// Spawn new entity in the swap pool with a given file name
+    /// which expires after given time
+    /// 
+    /// See `SwapEntity::set_ttl` and `SwapHandle::expire`
+    pub fn spawn_named_with_ttl(&mut self, name: impl AsRef<str>, value: T, ttl: Duration) -> SwapResult<Arc<SwapEntity<T>>> {
+        check_name(&self.handle, name.as_ref())?;
 
-        let entity = SwapEntity::create(value, self.handle.clone(), path, self.thread_safe)?;
+        let entity = SwapEntity::create(value, self.handle.clone(), name.as_ref(), self.thread_safe)?;
+
+        entity.set_ttl(ttl);
 
         Ok(self.handle.push_entity(entity))
     }
@@ -177,8 +549,8 @@ where
 impl<T> SwapPool<T>
 where
     T: TryFrom<Vec<u8>> + TryInto<Vec<u8>> + Clone + SizeOf + Hash,
-    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + 'static,
-    <T as TryInto<Vec<u8>>>::Error: std::error::Error + 'static
+    <T as TryFrom<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static,
+    <T as TryInto<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static
 {
     #[inline]
     /// Spawn new entity in the swap pool
@@ -196,6 +568,34 @@ where
     /// println!("Value len: {}", entity.value().unwrap().len());
     /// ```
     pub fn spawn(&mut self, value: T) -> SwapResult<Arc<SwapEntity<T>>> {
-        self.spawn_named(format!("{:x}.swap", uuid::get(&value)), value)
+        self.spawn_with_priority(value, SwapPriority::default())
+    }
+
+    #[inline]
+    /// Spawn new entity in the swap pool with a given eviction class
+    /// 
+    /// See `SwapEntity::set_priority`
+    pub fn spawn_with_priority(&mut self, value: T, priority: SwapPriority) -> SwapResult<Arc<SwapEntity<T>>> {
+        self.spawn_named_with_priority(self.generate_name(&value), value, priority)
+    }
+
+    #[inline]
+    /// Spawn new entity in the swap pool which expires after given time
+    /// 
+    /// See `SwapEntity::set_ttl` and `SwapHandle::expire`
+    pub fn spawn_with_ttl(&mut self, value: T, ttl: Duration) -> SwapResult<Arc<SwapEntity<T>>> {
+        self.spawn_named_with_ttl(self.generate_name(&value), value, ttl)
+    }
+
+    /// Generate unique swap name for the given value
+    fn generate_name(&self, value: &T) -> String {
+        let mut uuid = uuid::get(value);
+
+        // Equal values can get the same name
+        while self.handle.is_key_used(format!("{uuid:x}.swap")) {
+            uuid = uuid::get(uuid);
+        }
+
+        format!("{uuid:x}.swap")
     }
 }
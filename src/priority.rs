@@ -0,0 +1,35 @@
+/// Eviction class of the swap pool entity
+/// 
+/// When the pool needs some memory it flushes entities
+/// with lower priority first, and only then asks its
+/// manager to choose entities with higher priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum SwapPriority {
+    /// Entities which should be flushed first, e.g. bulk data
+    Low = 0,
+
+    #[default]
+    Normal = 1,
+
+    /// Entities which should be kept hot as long as possible, e.g. metadata
+    High = 2
+}
+
+impl SwapPriority {
+    /// All the priorities in the eviction order
+    pub const ALL: [Self; 3] = [Self::Low, Self::Normal, Self::High];
+
+    #[inline]
+    pub(crate) fn index(&self) -> usize {
+        *self as usize
+    }
+
+    #[inline]
+    pub(crate) fn from_index(index: u8) -> Self {
+        match index {
+            0 => Self::Low,
+            1 => Self::Normal,
+            _ => Self::High
+        }
+    }
+}
@@ -0,0 +1,817 @@
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, BTreeMap};
+use std::fs::File;
+
+use super::inplace_cell::InplaceCell;
+use super::manifest::{encode_hex, decode_hex_string};
+
+const SWAP_ARENA_INDEX_HEADER: &str = "swap-pool arena index v1";
+
+/// Storages are needed to keep entities values
+/// while they're cold
+/// 
+/// Values are identified by the entities' swap names
+/// (the ones given to `spawn_named`), which are unique
+/// within a swap pool
+pub trait SwapStorage: Send + Sync {
+    /// Read value stored with the given key
+    fn read(&self, key: &str) -> std::io::Result<Vec<u8>>;
+
+    /// Store value with the given key, overwriting the previous one
+    fn write(&self, key: &str, data: Vec<u8>) -> std::io::Result<()>;
+
+    /// Remove value stored with the given key
+    fn remove(&self, key: &str) -> std::io::Result<()>;
+
+    /// Check if there's a value stored with the given key
+    fn exists(&self, key: &str) -> bool;
+
+    /// Get size of the value stored with the given key
+    fn size(&self, key: &str) -> std::io::Result<usize>;
+
+    #[inline]
+    /// Reorganize stored values to reduce storage fragmentation
+    fn compact(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    /// Save storage's state so stored values can be read after reopening it
+    /// 
+    /// Persistent pools call it after saving their manifest
+    fn sync(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Store every entity in its own file in the given folder
+/// 
+/// This is the default swap pool storage
+pub struct SwapFsStorage {
+    folder: PathBuf
+}
+
+impl SwapFsStorage {
+    #[inline]
+    pub fn new(folder: impl Into<PathBuf>) -> Self {
+        Self {
+            folder: folder.into()
+        }
+    }
+
+    #[inline]
+    /// Get storage's folder
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    #[inline]
+    /// Get path to the swap file with the given key
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.folder.join(key)
+    }
+}
+
+impl SwapStorage for SwapFsStorage {
+    #[inline]
+    fn read(&self, key: &str) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.path(key))
+    }
+
+    #[inline]
+    fn write(&self, key: &str, data: Vec<u8>) -> std::io::Result<()> {
+        std::fs::write(self.path(key), data)
+    }
+
+    #[inline]
+    fn remove(&self, key: &str) -> std::io::Result<()> {
+        std::fs::remove_file(self.path(key))
+    }
+
+    #[inline]
+    fn exists(&self, key: &str) -> bool {
+        self.path(key).exists()
+    }
+
+    #[inline]
+    fn size(&self, key: &str) -> std::io::Result<usize> {
+        Ok(usize::try_from(self.path(key).metadata()?.len()).unwrap())
+    }
+}
+
+/// Keep cold entities' values in a `HashMap` in the RAM
+/// 
+/// Values are still transformed by the pool's transformer.
+/// This storage is useful for tests and benchmarks because
+/// it doesn't touch the disk, so different pools can't
+/// interfere with each other
+/// 
+/// ```rust
+/// use swap_pool::prelude::*;
+/// 
+/// let mut pool = SwapPoolBuilder::default()
+///     .with_storage(SwapMemoryStorage::default())
+///     .build(256, "");
+/// 
+/// let a = pool.spawn_named("a", vec![0; 64]).unwrap();
+/// let b = pool.spawn_named("b", vec![1; 64]).unwrap();
+/// 
+/// assert!(a.is_hot());
+/// assert!(b.is_cold());
+/// 
+/// // Read "b", making it hot and flushing "a"
+/// assert_eq!(b.value().unwrap(), vec![1; 64]);
+/// 
+/// assert!(a.is_cold());
+/// assert!(b.is_hot());
+/// ```
+#[derive(Default)]
+pub struct SwapMemoryStorage {
+    values: InplaceCell<HashMap<String, Vec<u8>>>
+}
+
+impl SwapMemoryStorage {
+    #[inline]
+    pub fn new(thread_safe: bool) -> Self {
+        Self {
+            values: InplaceCell::new(HashMap::new(), thread_safe)
+        }
+    }
+
+    #[inline]
+    /// Get amount of stored values
+    pub fn len(&self) -> usize {
+        self.values.get_ref().len()
+    }
+
+    #[inline]
+    /// Check if there's no values stored
+    pub fn is_empty(&self) -> bool {
+        self.values.get_ref().is_empty()
+    }
+}
+
+impl SwapStorage for SwapMemoryStorage {
+    #[inline]
+    fn read(&self, key: &str) -> std::io::Result<Vec<u8>> {
+        self.values.get_ref()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    }
+
+    #[inline]
+    fn write(&self, key: &str, data: Vec<u8>) -> std::io::Result<()> {
+        self.values.update(|values| {
+            values.insert(key.to_string(), data);
+        });
+
+        Ok(())
+    }
+
+    #[inline]
+    fn remove(&self, key: &str) -> std::io::Result<()> {
+        match self.values.get_mut().remove(key) {
+            Some(_) => Ok(()),
+            None => Err(std::io::ErrorKind::NotFound.into())
+        }
+    }
+
+    #[inline]
+    fn exists(&self, key: &str) -> bool {
+        self.values.get_ref().contains_key(key)
+    }
+
+    #[inline]
+    fn size(&self, key: &str) -> std::io::Result<usize> {
+        self.values.get_ref()
+            .get(key)
+            .map(Vec::len)
+            .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    }
+}
+
+struct SwapArena {
+    file: File,
+    capacity: u64,
+
+    /// Capacity the arena was created with.
+    /// The file is never shrunk below it
+    initial_capacity: u64,
+
+    /// Stored values' offsets and lengths
+    entries: HashMap<String, (u64, u64)>,
+
+    /// Free extents' offsets and lengths
+    free: BTreeMap<u64, u64>
+}
+
+impl SwapArena {
+    /// Find free extent for the value with given length,
+    /// growing the arena file if there's none
+    fn allocate(&mut self, len: u64) -> std::io::Result<u64> {
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let extent = self.free.iter()
+            .find(|(_, free_len)| **free_len >= len)
+            .map(|(offset, free_len)| (*offset, *free_len));
+
+        let (offset, free_len) = match extent {
+            Some(extent) => extent,
+
+            // Double the arena file size (or more if it's not enough)
+            None => {
+                let grow = self.capacity.max(len);
+
+                self.file.set_len(self.capacity + grow)?;
+                self.release(self.capacity, grow);
+
+                self.capacity += grow;
+
+                return self.allocate(len);
+            }
+        };
+
+        self.free.remove(&offset);
+
+        if free_len > len {
+            self.free.insert(offset + len, free_len - len);
+        }
+
+        Ok(offset)
+    }
+
+    /// Mark extent as free, merging it with its neighbours
+    fn release(&mut self, mut offset: u64, mut len: u64) {
+        if len == 0 {
+            return;
+        }
+
+        let prev = self.free.range(..offset)
+            .next_back()
+            .map(|(prev_offset, prev_len)| (*prev_offset, *prev_len));
+
+        if let Some((prev_offset, prev_len)) = prev {
+            if prev_offset + prev_len == offset {
+                self.free.remove(&prev_offset);
+
+                offset = prev_offset;
+                len += prev_len;
+            }
+        }
+
+        if let Some(next_len) = self.free.remove(&(offset + len)) {
+            len += next_len;
+        }
+
+        self.free.insert(offset, len);
+    }
+
+    /// Move stored values to the beginning of the file
+    /// 
+    /// Return the end of the last moved value
+    fn move_values(&mut self) -> std::io::Result<u64> {
+        let mut entries = self.entries.iter()
+            .map(|(key, (offset, len))| (*offset, *len, key.clone()))
+            .collect::<Vec<_>>();
+
+        entries.sort_unstable();
+
+        // Move values one by one to the beginning of the file.
+        // Values are sorted by their offsets so we will never
+        // overwrite a value which is not moved yet
+        let mut cursor = 0;
+
+        for (offset, len, key) in entries {
+            if len == 0 {
+                continue;
+            }
+
+            if offset != cursor {
+                let mut source = offset;
+
+                // Writing the value over its own extent would corrupt it
+                // if writing fails, so copy it past the end of the file first
+                if cursor + len > offset {
+                    let tail = self.capacity;
+
+                    self.file.set_len(tail + len)?;
+                    self.capacity += len;
+
+                    self.copy(source, tail, len)?;
+                    self.entries.insert(key.clone(), (tail, len));
+
+                    source = tail;
+                }
+
+                self.copy(source, cursor, len)?;
+                self.entries.insert(key, (cursor, len));
+            }
+
+            cursor += len;
+        }
+
+        Ok(cursor)
+    }
+
+    /// Shrink the arena file to the given size
+    fn shrink(&mut self, capacity: u64) -> std::io::Result<()> {
+        if capacity < self.capacity {
+            self.file.set_len(capacity)?;
+
+            self.capacity = capacity;
+        }
+
+        Ok(())
+    }
+
+    /// Recalculate free extents using the stored values
+    fn rebuild_free(&mut self) {
+        let mut entries = self.entries.values()
+            .filter(|(_, len)| *len > 0)
+            .copied()
+            .collect::<Vec<_>>();
+
+        entries.sort_unstable();
+
+        self.free.clear();
+
+        let mut cursor = 0;
+
+        for (offset, len) in entries {
+            if offset > cursor {
+                self.free.insert(cursor, offset - cursor);
+            }
+
+            cursor = cursor.max(offset + len);
+        }
+
+        if self.capacity > cursor {
+            self.free.insert(cursor, self.capacity - cursor);
+        }
+    }
+
+    /// Serialize stored values' offsets and lengths
+    fn index(&self) -> Vec<u8> {
+        let mut index = format!("{SWAP_ARENA_INDEX_HEADER}\n");
+
+        for (key, (offset, len)) in &self.entries {
+            index += &format!("value {offset} {len} {}\n", encode_hex(key.as_bytes()));
+        }
+
+        index.into_bytes()
+    }
+
+    /// Deserialize stored values' offsets and lengths
+    fn parse_index(index: &[u8], capacity: u64) -> std::io::Result<HashMap<String, (u64, u64)>> {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid swap arena index: {message}"));
+
+        let index = std::str::from_utf8(index)
+            .map_err(|_| invalid("not a valid utf-8 string"))?;
+
+        let mut lines = index.lines();
+
+        if lines.next() != Some(SWAP_ARENA_INDEX_HEADER) {
+            return Err(invalid("unsupported header"));
+        }
+
+        let mut entries = HashMap::new();
+
+        for line in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut words = line.split(' ');
+
+            if words.next() != Some("value") {
+                return Err(invalid(line));
+            }
+
+            let offset = words.next().and_then(|offset| offset.parse::<u64>().ok());
+            let len = words.next().and_then(|len| len.parse::<u64>().ok());
+            let key = words.next().and_then(decode_hex_string);
+
+            let (Some(offset), Some(len), Some(key)) = (offset, len, key) else {
+                return Err(invalid(line));
+            };
+
+            // Values must be stored in the file
+            if offset.checked_add(len).is_none_or(|end| end > capacity) {
+                return Err(invalid(line));
+            }
+
+            entries.insert(key, (offset, len));
+        }
+
+        Ok(entries)
+    }
+
+    /// Copy value to another extent
+    fn copy(&self, from: u64, to: u64, len: u64) -> std::io::Result<()> {
+        let data = self.read_at(from, len)?;
+
+        self.write_at(to, &data)
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+        use std::os::unix::fs::FileExt;
+
+        let mut data = vec![0; usize::try_from(len).unwrap()];
+
+        self.file.read_exact_at(&mut data, offset)?;
+
+        Ok(data)
+    }
+
+    #[cfg(unix)]
+    fn write_at(&self, offset: u64, data: &[u8]) -> std::io::Result<()> {
+        use std::os::unix::fs::FileExt;
+
+        self.file.write_all_at(data, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+        use std::os::windows::fs::FileExt;
+
+        let mut data = vec![0; usize::try_from(len).unwrap()];
+        let mut read = 0;
+
+        while read < data.len() {
+            match self.file.seek_read(&mut data[read..], offset + read as u64)? {
+                0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                n => read += n
+            }
+        }
+
+        Ok(data)
+    }
+
+    #[cfg(windows)]
+    fn write_at(&self, offset: u64, data: &[u8]) -> std::io::Result<()> {
+        use std::os::windows::fs::FileExt;
+
+        let mut written = 0;
+
+        while written < data.len() {
+            match self.file.seek_write(&data[written..], offset + written as u64)? {
+                0 => return Err(std::io::ErrorKind::WriteZero.into()),
+                n => written += n
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Store all the entities in a single preallocated swap file
+/// 
+/// Freed space is reused by the following writes, and the file
+/// grows when there's no free extent large enough for a value.
+/// Call `compact` (or `SwapHandle::compact`) to move all the
+/// values to the beginning of the file and shrink it back
+/// (but not below its initial capacity)
+/// 
+/// The swap file is removed when the storage is dropped,
+/// unless it was opened by `SwapArenaStorage::open`
+/// 
+/// ```rust
+/// use swap_pool::prelude::*;
+/// 
+/// let path = std::env::temp_dir().join("swap-pool-arena-pool.swap");
+/// 
+/// let storage = SwapArenaStorage::new(&path, 1024 * 1024).unwrap();
+/// 
+/// let mut pool = SwapPoolBuilder::default()
+///     .with_storage(storage)
+///     .build(128, "");
+/// 
+/// let entity = pool.spawn(vec![1; 1024]).unwrap();
+/// 
+/// // The value is stored in the arena file
+/// entity.flush().unwrap();
+/// 
+/// // Reorganize the swap file
+/// pool.handle().compact().unwrap();
+/// 
+/// assert_eq!(entity.value().unwrap(), vec![1; 1024]);
+/// 
+/// drop(entity);
+/// drop(pool);
+/// 
+/// // The file is removed with the storage
+/// assert!(!path.exists());
+/// ```
+pub struct SwapArenaStorage {
+    path: PathBuf,
+    arena: InplaceCell<SwapArena>,
+
+    /// Persistent arenas keep the swap file
+    /// and save its index when dropped
+    persistent: bool
+}
+
+impl SwapArenaStorage {
+    #[inline]
+    /// Create new swap file with the given capacity in bytes
+    /// 
+    /// Existing file will be overwritten
+    pub fn new(path: impl Into<PathBuf>, capacity: u64) -> std::io::Result<Self> {
+        Self::create(path.into(), capacity, false)
+    }
+
+    /// Open swap file saved by the persistent arena,
+    /// or create new one with the given capacity in bytes
+    /// 
+    /// Unlike `new`, the swap file is kept when the storage is dropped,
+    /// and the stored values' index is saved next to it (see `index_path`)
+    /// by `sync` and on drop, so this storage can be used by persistent pools
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let path = std::env::temp_dir().join("swap-pool-arena-open.swap");
+    /// 
+    /// # let _ = std::fs::remove_file(&path);
+    /// # let _ = std::fs::remove_file(SwapArenaStorage::index_path(&path));
+    /// let storage = SwapArenaStorage::open(&path, 1024).unwrap();
+    /// 
+    /// storage.write("value", vec![1; 128]).unwrap();
+    /// 
+    /// drop(storage);
+    /// 
+    /// // Stored values are available after reopening the arena
+    /// let storage = SwapArenaStorage::open(&path, 1024).unwrap();
+    /// 
+    /// assert_eq!(storage.read("value").unwrap(), vec![1; 128]);
+    /// assert_eq!(storage.used(), 128);
+    /// ```
+    pub fn open(path: impl Into<PathBuf>, capacity: u64) -> std::io::Result<Self> {
+        let path: PathBuf = path.into();
+
+        let index = match std::fs::read(Self::index_path(&path)) {
+            Ok(index) => index,
+
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Self::create(path, capacity, true);
+            }
+
+            Err(err) => return Err(err)
+        };
+
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .open(&path)?;
+
+        let file_len = file.metadata()?.len();
+
+        let mut arena = SwapArena {
+            file,
+            capacity: file_len,
+            initial_capacity: capacity,
+            entries: SwapArena::parse_index(&index, file_len)?,
+            free: BTreeMap::new()
+        };
+
+        arena.rebuild_free();
+
+        Ok(Self {
+            path,
+            arena: InplaceCell::new(arena, true),
+            persistent: true
+        })
+    }
+
+    fn create(path: PathBuf, capacity: u64, persistent: bool) -> std::io::Result<Self> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+
+        file.set_len(capacity)?;
+
+        let mut free = BTreeMap::new();
+
+        if capacity > 0 {
+            free.insert(0, capacity);
+        }
+
+        Ok(Self {
+            path,
+            arena: InplaceCell::new(SwapArena {
+                file,
+                capacity,
+                initial_capacity: capacity,
+                entries: HashMap::new(),
+                free
+            }, true),
+            persistent
+        })
+    }
+
+    #[inline]
+    /// Get path to the swap file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    /// Get path to the index of the persistent swap file
+    pub fn index_path(path: &Path) -> PathBuf {
+        let mut index = path.as_os_str().to_owned();
+
+        index.push(".index");
+
+        PathBuf::from(index)
+    }
+
+    #[inline]
+    /// Get current swap file size
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let path = std::env::temp_dir().join("swap-pool-arena-capacity.swap");
+    /// 
+    /// let storage = SwapArenaStorage::new(&path, 1000).unwrap();
+    /// 
+    /// // The file grows when there's no free extent for a value
+    /// for i in 0..10 {
+    ///     storage.write(&format!("value {i}"), vec![i; 1000]).unwrap();
+    /// }
+    /// 
+    /// assert_eq!(storage.capacity(), 16000);
+    /// assert_eq!(std::fs::metadata(&path).unwrap().len(), 16000);
+    /// 
+    /// for i in 0..9 {
+    ///     storage.remove(&format!("value {i}")).unwrap();
+    /// }
+    /// 
+    /// // Compaction moves values to the beginning of the file
+    /// // and shrinks it, but not below the initial capacity
+    /// storage.compact().unwrap();
+    /// 
+    /// assert_eq!(storage.capacity(), 1000);
+    /// assert_eq!(std::fs::metadata(&path).unwrap().len(), 1000);
+    /// 
+    /// assert_eq!(storage.read("value 9").unwrap(), vec![9; 1000]);
+    /// ```
+    pub fn capacity(&self) -> u64 {
+        self.arena.get_ref().capacity
+    }
+
+    #[inline]
+    /// Get amount of bytes used by the stored values
+    /// 
+    /// ```rust
+    /// use swap_pool::prelude::*;
+    /// 
+    /// let path = std::env::temp_dir().join("swap-pool-arena-used.swap");
+    /// 
+    /// let storage = SwapArenaStorage::new(&path, 300).unwrap();
+    /// 
+    /// storage.write("a", vec![0; 100]).unwrap();
+    /// storage.write("b", vec![1; 100]).unwrap();
+    /// storage.write("c", vec![2; 100]).unwrap();
+    /// 
+    /// assert_eq!(storage.used(), 300);
+    /// 
+    /// // Freed extent is reused by the following writes
+    /// storage.remove("b").unwrap();
+    /// storage.write("d", vec![3; 100]).unwrap();
+    /// 
+    /// assert_eq!(storage.capacity(), 300);
+    /// 
+    /// // Neighbour free extents are merged
+    /// storage.remove("a").unwrap();
+    /// storage.remove("d").unwrap();
+    /// storage.write("e", vec![4; 200]).unwrap();
+    /// 
+    /// assert_eq!(storage.used(), 300);
+    /// assert_eq!(storage.capacity(), 300);
+    /// 
+    /// assert_eq!(storage.read("c").unwrap(), vec![2; 100]);
+    /// assert_eq!(storage.read("e").unwrap(), vec![4; 200]);
+    /// ```
+    pub fn used(&self) -> u64 {
+        self.arena.get_ref().entries
+            .values()
+            .map(|(_, len)| len)
+            .sum()
+    }
+}
+
+impl SwapStorage for SwapArenaStorage {
+    fn read(&self, key: &str) -> std::io::Result<Vec<u8>> {
+        // Positional reads don't need an exclusive lock
+        let arena = self.arena.get_ref();
+
+        let Some((offset, len)) = arena.entries.get(key).copied() else {
+            return Err(std::io::ErrorKind::NotFound.into());
+        };
+
+        arena.read_at(offset, len)
+    }
+
+    fn write(&self, key: &str, data: Vec<u8>) -> std::io::Result<()> {
+        let mut arena = self.arena.get_mut();
+
+        // Keep the previous value until the new one is written
+        let len = data.len() as u64;
+        let offset = arena.allocate(len)?;
+
+        if let Err(err) = arena.write_at(offset, &data) {
+            arena.release(offset, len);
+
+            return Err(err);
+        }
+
+        if let Some((offset, len)) = arena.entries.insert(key.to_string(), (offset, len)) {
+            arena.release(offset, len);
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> std::io::Result<()> {
+        let mut arena = self.arena.get_mut();
+
+        let Some((offset, len)) = arena.entries.remove(key) else {
+            return Err(std::io::ErrorKind::NotFound.into());
+        };
+
+        arena.release(offset, len);
+
+        Ok(())
+    }
+
+    #[inline]
+    fn exists(&self, key: &str) -> bool {
+        self.arena.get_ref().entries.contains_key(key)
+    }
+
+    #[inline]
+    fn size(&self, key: &str) -> std::io::Result<usize> {
+        match self.arena.get_ref().entries.get(key) {
+            Some((_, len)) => Ok(usize::try_from(*len).unwrap()),
+            None => Err(std::io::ErrorKind::NotFound.into())
+        }
+    }
+
+    fn compact(&self) -> std::io::Result<()> {
+        let mut arena = self.arena.get_mut();
+
+        let initial_capacity = arena.initial_capacity;
+
+        let result = arena.move_values()
+            .and_then(|cursor| arena.shrink(cursor.max(initial_capacity)));
+
+        // Some values could be moved before an error happened
+        // so old free extents can overlap them
+        arena.rebuild_free();
+
+        result
+    }
+
+    fn sync(&self) -> std::io::Result<()> {
+        if !self.persistent {
+            return Ok(());
+        }
+
+        let arena = self.arena.get_ref();
+
+        arena.file.sync_data()?;
+
+        // Replace the index at once so it's never
+        // read partially written
+        let index_path = Self::index_path(&self.path);
+
+        let mut temp_path = index_path.clone().into_os_string();
+
+        temp_path.push(".tmp");
+
+        std::fs::write(&temp_path, arena.index())?;
+        std::fs::rename(&temp_path, &index_path)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for SwapArenaStorage {
+    #[inline]
+    fn drop(&mut self) {
+        // TODO: panic?
+        if self.persistent {
+            let _ = self.sync();
+        } else {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
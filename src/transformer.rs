@@ -3,24 +3,40 @@
 /// 
 /// You can use transformers to implement swap files compression
 /// or any other operation
-pub trait SwapTransformer {
+pub trait SwapTransformer: Send + Sync {
     /// Mutate entity value before saving it to the swap file
-    fn forward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+    fn forward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
 
     /// Mutate swap file value before loading it to the entity
-    fn backward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+    fn backward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+
+    #[inline]
+    /// Transformer's unique id
+    /// 
+    /// It's saved in persistent pools' manifests to ensure
+    /// that stored values will be read by the same transformer,
+    /// so it must not change between program versions.
+    /// Transformers without id can't be used by persistent pools
+    fn id(&self) -> Option<&str> {
+        None
+    }
 }
 
 pub struct SwapIdentityTransformer;
 
 impl SwapTransformer for SwapIdentityTransformer {
     #[inline]
-    fn forward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fn forward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
         Ok(data)
     }
 
     #[inline]
-    fn backward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fn backward(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
         Ok(data)
     }
+
+    #[inline]
+    fn id(&self) -> Option<&str> {
+        Some("identity")
+    }
 }
@@ -0,0 +1,89 @@
+use std::sync::{Arc, Mutex, mpsc};
+use std::panic::AssertUnwindSafe;
+
+type SwapTask = Box<dyn FnOnce() + Send>;
+
+/// Reusable threads running the pool's batch operations
+pub struct SwapWorkers {
+    sender: Mutex<mpsc::Sender<SwapTask>>,
+    threads: usize
+}
+
+impl SwapWorkers {
+    /// Spawn given amount of worker threads
+    /// 
+    /// Workers are stopped when this struct is dropped
+    pub fn new(threads: usize) -> std::io::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<SwapTask>();
+
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..threads {
+            let receiver = receiver.clone();
+
+            std::thread::Builder::new()
+                .name(format!("swap-pool-worker-{i}"))
+                .spawn(move || loop {
+                    // The receiver is unlocked before running the task
+                    let task = receiver.lock()
+                        .unwrap_or_else(|err| err.into_inner())
+                        .recv();
+
+                    match task {
+                        Ok(task) => task(),
+
+                        // Workers were dropped
+                        Err(_) => break
+                    }
+                })?;
+        }
+
+        Ok(Self {
+            sender: Mutex::new(sender),
+            threads
+        })
+    }
+
+    #[inline]
+    /// Get amount of worker threads
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Run given tasks and return their results in the same order
+    /// 
+    /// Panics of the tasks are resumed in the current thread
+    pub fn run<R: Send + 'static>(&self, tasks: Vec<Box<dyn FnOnce() -> R + Send>>) -> Vec<R> {
+        let (sender, receiver) = mpsc::channel();
+
+        {
+            let workers = self.sender.lock().unwrap_or_else(|err| err.into_inner());
+
+            for (i, task) in tasks.into_iter().enumerate() {
+                let sender = sender.clone();
+
+                let task: SwapTask = Box::new(move || {
+                    let result = std::panic::catch_unwind(AssertUnwindSafe(task));
+
+                    let _ = sender.send((i, result));
+                });
+
+                // Run the task in the current thread if workers are stopped
+                if let Err(mpsc::SendError(task)) = workers.send(task) {
+                    task();
+                }
+            }
+        }
+
+        drop(sender);
+
+        // Wait until all the tasks are finished
+        let mut results = receiver.iter().collect::<Vec<_>>();
+
+        results.sort_unstable_by_key(|(i, _)| *i);
+
+        results.into_iter()
+            .map(|(_, result)| result.unwrap_or_else(|err| std::panic::resume_unwind(err)))
+            .collect()
+    }
+}